@@ -6,7 +6,7 @@ stdenv.mkDerivation {
   name = "switcher";
   buildInputs = [
     ((rustChannelOf {
-      date = "2018-09-08";
+      date = "2022-03-01";
       channel = "nightly";
     }).rust.override {
       targets = [ "thumbv7m-none-eabi" ];
@@ -14,11 +14,32 @@
 
 //! Functions related to the calculation and verification of CRC-24 sums.
 
+use core::ptr;
+use flash::FlashWriter;
+use VERIFY_CHUNK_BYTES;
+
 /// CRC polynomial taken from [Wikipedia][poly].
 ///
 /// [poly]: https://en.wikipedia.org/wiki/Cyclic_redundancy_check.
 pub static CRC_POLYNOMIAL: u32 = 0x005D_6DCB;
 
+/// The approximate number of cycles taken to pass a single byte through the CRC.
+///
+/// This is an estimate for a Cortex-M3, based on the bitwise loop taking roughly eight cycles per
+/// bit, and not a measurement. It is intended for conservative estimates of verification time.
+pub const CYCLES_PER_BYTE: u32 = 64;
+
+/// CRC-16 polynomial (CRC-16/CCITT), used to protect the footer's metadata.
+pub static CRC16_POLYNOMIAL: u32 = 0x1021;
+
+/// The width of the CRC sum, in bytes.
+///
+/// This is also the number of zero bytes that are appended to a message when calculating its sum.
+pub const CRC_WIDTH_BYTES: usize = 3;
+
+/// The width of the CRC sum, in bits.
+pub const CRC_WIDTH_BITS: usize = 8 * CRC_WIDTH_BYTES;
+
 /// Calculates the sum of data passed through the 24-bit CRC.
 ///
 /// The sum will reside in the least significant bytes of the returned `u32`.
@@ -73,7 +94,7 @@ pub static CRC_POLYNOMIAL: u32 = 0x005D_6DCB;
 //                                  A   7   6   2   9   E
 // ```
 pub fn calculate(data: &[u8]) -> u32 {
-    crc(data.iter().chain(&[0, 0, 0]))
+    crc(data.iter().chain(&[0; CRC_WIDTH_BYTES]))
 }
 
 /// Returns true if the given byte slice is valid.
@@ -130,37 +151,496 @@ pub fn calculate(data: &[u8]) -> u32 {
 // 00000000000000000000000000000000 000000000000000000000000
 // ```
 pub fn is_valid(data: &[u8]) -> bool {
+    // Data shorter than the sum is never reduced by the polynomial, so its remainder is the data
+    // itself and it can only be valid if it is all zeros.
+    if data.len() < CRC_WIDTH_BYTES {
+        return data.iter().all(|byte| *byte == 0);
+    }
+
     crc(data.iter()) == 0
 }
 
-/// Calculates the sum of data passed through the 24-bit CRC. The sum will reside in the least
-/// significant bytes of the returned `u32`.
-fn crc<'a, T: Iterator<Item = &'a u8>>(data: T) -> u32 {
-    fn shift_left(val: u32) -> (u32, bool) {
-        let carry = (val & (1 << 31)) != 0;
-        (val << 1, carry)
+/// Returns whether the given byte slice is valid, along with the sum it should have ended with.
+///
+/// As with [`is_valid()`][is_valid], the byte slice must consist of the payload followed
+/// immediately by its CRC-24 sum: the final [`CRC_WIDTH_BYTES`][width] bytes are the sum and
+/// everything before them is the payload. The second element is the sum of the payload (see
+/// [`calculate()`][calculate]), which is what the final bytes should have been had the data been
+/// valid. Input shorter than the sum is taken to have an empty payload. Both results are
+/// computed in a single pass over the data.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::check;
+/// assert_eq!(check(&[0x41, 0x42, 0x43, 0x44, 0xA7, 0x62, 0x9E]), (true, 0xA7629E));
+/// assert_eq!(check(&[0x41, 0x42, 0x43, 0x44, 0x00, 0x00, 0x00]), (false, 0xA7629E));
+/// ```
+///
+/// [is_valid]: fn.is_valid.html
+/// [width]: constant.CRC_WIDTH_BYTES.html
+/// [calculate]: fn.calculate.html
+pub fn check(data: &[u8]) -> (bool, u32) {
+    let (payload, sum) = data.split_at(data.len().saturating_sub(CRC_WIDTH_BYTES));
+
+    let mut crc = Crc::new();
+    crc.update(payload);
+    let expected = crc.checksum();
+    crc.update(sum);
+    (crc.remainder() == 0, expected)
+}
+
+/// Returns true if the given byte slice is valid under any of the given 24-bit polynomials.
+///
+/// This is identical to [`is_valid()`][is_valid], except that the sum may have been calculated
+/// with any one of the polynomials. They are tried in order, stopping at the first under which
+/// the data is valid.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::{is_valid_any, CRC_POLYNOMIAL};
+/// let data = [0x41, 0x42, 0x43, 0x44, 0xA7, 0x62, 0x9E];
+/// assert!(is_valid_any(&data, &[0x864CFB, CRC_POLYNOMIAL]));
+/// assert!(!is_valid_any(&data, &[0x864CFB]));
+/// ```
+///
+/// [is_valid]: fn.is_valid.html
+pub fn is_valid_any(data: &[u8], polys: &[u32]) -> bool {
+    polys.iter().any(|poly| {
+        let mut crc = Crc::<24>::with_polynomial(*poly);
+        crc.update(data);
+        crc.remainder() == 0
+    })
+}
+
+/// Returns true if the CRC produces the expected sum for a known message.
+///
+/// This is a known-answer test, intended to be run at startup before any checksum is trusted. It
+/// calculates the sum of `ABCD` (see [`calculate()`][calculate]) and compares it against the
+/// expected `0xA7629E`; a mismatch indicates that the hardware is misbehaving and that the
+/// caller should halt.
+///
+/// [calculate]: fn.calculate.html
+pub fn self_test() -> bool {
+    // Prevent the calculation from being evaluated at compile time.
+    let message = unsafe { ptr::read_volatile(&[0x41, 0x42, 0x43, 0x44]) };
+    calculate(&message) == 0xA7629E
+}
+
+/// Calculates the sum of data, given as 32-bit words, passed through the 24-bit CRC.
+///
+/// Each word is expanded into bytes in little-endian order (least significant byte first), which
+/// is the order in which a little-endian core, such as the Cortex-M, stores it in memory. The
+/// result is therefore identical to that of [`calculate()`][calculate] over the bytes of the
+/// words as they sit in flash.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::calculate_words;
+/// assert_eq!(calculate_words(&[0x44434241]), 0xA7629E);
+/// ```
+///
+/// [calculate]: fn.calculate.html
+pub fn calculate_words(words: &[u32]) -> u32 {
+    let mut crc = Crc::new();
+    for word in words {
+        crc.update(&[
+            *word as u8,
+            (word >> 8) as u8,
+            (word >> 16) as u8,
+            (word >> 24) as u8,
+        ]);
     }
+    crc.checksum()
+}
 
-    // The actual CRC remainder is stored in the three most significant bytes
-    // of crc. The least significant byte holds the next byte of the message to
-    // be shifted through the CRC.
-    let mut crc: u32 = 0;
+/// Calculates the sum of the concatenation of the given segments passed through the 24-bit CRC.
+///
+/// The result is identical to that of [`calculate()`][calculate] over the concatenated data,
+/// regardless of where the segment boundaries lie.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::calculate_segments;
+/// assert_eq!(calculate_segments(&[&[0x41, 0x42], &[0x43, 0x44]]), 0xA7629E);
+/// ```
+///
+/// [calculate]: fn.calculate.html
+pub fn calculate_segments(segments: &[&[u8]]) -> u32 {
+    let mut crc = Crc::new();
+    for segment in segments {
+        crc.update(segment);
+    }
+    crc.checksum()
+}
 
-    for byte in data {
-        // Set up the next byte in the holding area...
-        crc |= u32::from(*byte);
-
-        // ...and shift it through the CRC (assuming an 8-bit byte).
-        for _ in 0..8 {
-            crc = match shift_left(crc) {
-                (crc, false) => crc,
-                (crc, true) => crc ^ (CRC_POLYNOMIAL << 8),
+/// Returns true if the concatenation of the given segments is valid.
+///
+/// The final segments must end with the CRC-24 sum of the preceding data. The result is identical
+/// to that of [`is_valid()`][is_valid] over the concatenated data, regardless of where the segment
+/// boundaries lie.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::is_valid_segments;
+/// assert_eq!(is_valid_segments(&[&[0x41, 0x42, 0x43], &[0x44, 0xA7], &[0x62, 0x9E]]), true);
+/// ```
+///
+/// [is_valid]: fn.is_valid.html
+pub fn is_valid_segments(segments: &[&[u8]]) -> bool {
+    let mut crc = Crc::new();
+    for segment in segments {
+        crc.update(segment);
+    }
+    crc.remainder() == 0
+}
+
+/// Returns true if the concatenation of the given chunks has the given CRC-24 sum.
+///
+/// Unlike [`is_valid_segments()`][is_valid_segments], the sum is supplied separately rather than
+/// following the data, and the chunks are consumed as they are produced. This allows an image
+/// to be verified as it is decompressed, without holding all of it in memory.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::is_valid_stream;
+/// let chunks = [&[0x41][..], &[0x42, 0x43], &[0x44]];
+/// assert!(is_valid_stream(chunks.iter().cloned(), 0xA7629E));
+/// ```
+///
+/// [is_valid_segments]: fn.is_valid_segments.html
+pub fn is_valid_stream<'a, T: Iterator<Item = &'a [u8]>>(chunks: T, checksum: u32) -> bool {
+    let mut crc = Crc::new();
+    for chunk in chunks {
+        crc.update(chunk);
+    }
+    crc.checksum() == checksum
+}
+
+/// Calculates the IEEE 802.3 CRC-32 of the given data, as computed by zlib's `crc32()`.
+///
+/// This is the reflected CRC-32 (polynomial 0x04C11DB7, initial value and final XOR of
+/// 0xFFFFFFFF), offered for images which were checksummed with common host tools. It is not
+/// the CRC which the rest of this crate uses to verify images.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::ieee_crc32;
+/// assert_eq!(ieee_crc32(b"123456789"), 0xCBF43926);
+/// ```
+pub fn ieee_crc32(data: &[u8]) -> u32 {
+    let mut crc = IeeeCrc32::new();
+    crc.update(data);
+    crc.checksum()
+}
+
+/// Compares two sums in constant time.
+///
+/// The time taken doesn't depend on where (or whether) the values differ, so this is suited to
+/// comparing secret values, such as a MAC, against an expected one. Verifying an image's CRC
+/// doesn't need it: the sum isn't secret, since anyone holding the image can compute it.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::ct_eq;
+/// assert!(ct_eq(0xA7629E, 0xA7629E));
+/// assert!(!ct_eq(0xA7629E, 0xA7629F));
+/// ```
+pub fn ct_eq(a: u32, b: u32) -> bool {
+    let diff = a ^ b;
+    // The top bit of `diff | -diff` is set if and only if `diff` is non-zero. The volatile read
+    // keeps the compiler from turning this back into an early-exit comparison.
+    let unequal = (diff | diff.wrapping_neg()) >> 31;
+    unsafe { ptr::read_volatile(&unequal) == 0 }
+}
+
+/// Calculates the CRC-16/CCITT of the given data.
+///
+/// This is the unreflected CRC-16 with polynomial 0x1021 and an initial value of 0xFFFF (also
+/// known as CRC-16/CCITT-FALSE), used by some peripherals and host tools to check blocks of data.
+/// Like [`ieee_crc32()`][ieee_crc32], it plays no part in verifying images.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::ccitt16;
+/// assert_eq!(ccitt16(b"123456789"), 0x29B1);
+/// ```
+///
+/// [ieee_crc32]: fn.ieee_crc32.html
+pub fn ccitt16(data: &[u8]) -> u16 {
+    let mut crc = Crc::<16>::with_polynomial_and_init(0x1021, 0xFFFF);
+    crc.update(data);
+    crc.checksum() as u16
+}
+
+/// A streaming calculation of a CRC, `BITS` wide.
+///
+/// Data may be fed through [`update()`][update] in any number of pieces; the result is the same
+/// as if it had been fed all at once. Any width from 1 to 32 bits is supported; `Crc<24>` (the
+/// default) uses [`CRC_POLYNOMIAL`][poly] and is the CRC used throughout the rest of this crate.
+///
+/// [update]: #method.update
+/// [poly]: static.CRC_POLYNOMIAL.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crc<const BITS: usize = 24> {
+    polynomial: u32,
+    // The remainder of the message bits that have been shifted through so far,
+    // occupying the least significant BITS bits.
+    crc: u32,
+}
+
+impl Crc<24> {
+    /// Creates a new CRC-24 calculation over no data.
+    pub fn new() -> Crc<24> {
+        Crc::with_polynomial(CRC_POLYNOMIAL)
+    }
+
+    /// Creates a new CRC-24 calculation over no data, with the given initial value.
+    ///
+    /// The initial value is interpreted as it is by most CRC definitions (e.g. CRC-24/FLEXRAY-A
+    /// uses this polynomial with an initial value of `0xFEDCBA`): it is the value of the 24-bit
+    /// register before the first bit of the message is shifted in. An initial value of zero is
+    /// identical to [`new()`][new].
+    ///
+    /// [new]: #method.new
+    pub fn with_init(init: u32) -> Crc<24> {
+        Crc::with_polynomial_and_init(CRC_POLYNOMIAL, init)
+    }
+}
+
+impl Default for Crc<24> {
+    fn default() -> Crc<24> {
+        Crc::new()
+    }
+}
+
+impl<const BITS: usize> Crc<BITS> {
+    // The bits of the register. Evaluating this fails to compile for widths outside of 1..=32.
+    const MASK: u32 = !0 >> (32 - BITS);
+
+    /// Creates a new calculation over no data, using the given polynomial.
+    ///
+    /// The polynomial is given in its normal representation, without the implicit `x^BITS` term;
+    /// any bits above the width of the CRC are ignored.
+    pub fn with_polynomial(polynomial: u32) -> Crc<BITS> {
+        Crc {
+            polynomial: polynomial & Self::MASK,
+            crc: 0,
+        }
+    }
+
+    /// Creates a new calculation over no data, using the given polynomial and initial value.
+    ///
+    /// See [`Crc::<24>::with_init()`][with_init] for the interpretation of the initial value.
+    ///
+    /// [with_init]: #method.with_init
+    pub fn with_polynomial_and_init(polynomial: u32, init: u32) -> Crc<BITS> {
+        let mut crc = Crc::with_polynomial(polynomial);
+
+        // This implementation appends zeros to the message so that its register holds the
+        // remainder of the message bits that have been shifted through so far. A conventional
+        // register, on the other hand, is already BITS bits ahead. The equivalent starting
+        // remainder is found by shifting the initial value backward through the CRC by BITS bits
+        // (which relies upon the constant term of the polynomial being set, as it always is).
+        let top = 1 << (BITS - 1);
+        let mut remainder = init & Self::MASK;
+        for _ in 0..BITS {
+            remainder = if remainder & 1 == 0 {
+                remainder >> 1
+            } else {
+                ((remainder ^ crc.polynomial) >> 1) | top
             };
         }
+        crc.crc = remainder;
+        crc
     }
 
-    // Extract the remainder (assuming an 8-bit byte).
-    crc >> 8
+    /// Passes the given data through the CRC.
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.push(*byte)
+        }
+    }
+
+    /// Returns the remainder of the data passed through the CRC so far.
+    ///
+    /// If the data consisted of a payload followed by its CRC-24 sum, the remainder is zero (see
+    /// [`is_valid()`][is_valid]). The same holds for other widths that are a multiple of eight
+    /// bits.
+    ///
+    /// [is_valid]: fn.is_valid.html
+    pub fn remainder(&self) -> u32 {
+        self.crc
+    }
+
+    /// Returns the sum of the data passed through the CRC so far (see
+    /// [`calculate()`][calculate]).
+    ///
+    /// The sum is the remainder after `BITS` zero bits have been appended to the data.
+    ///
+    /// [calculate]: fn.calculate.html
+    pub fn checksum(&self) -> u32 {
+        let mut crc = *self;
+        for _ in 0..BITS {
+            crc.shift(false);
+        }
+        crc.remainder()
+    }
+
+    fn push(&mut self, byte: u8) {
+        for i in (0..8).rev() {
+            self.shift(byte & (1 << i) != 0);
+        }
+    }
+
+    // Shifts a single bit of the message into the register, reducing it by the polynomial if the
+    // bit shifted out of the top of the register was set.
+    fn shift(&mut self, bit: bool) {
+        let carry = self.crc & (1 << (BITS - 1)) != 0;
+        self.crc = ((self.crc << 1) | u32::from(bit)) & Self::MASK;
+        if carry {
+            self.crc ^= self.polynomial;
+        }
+    }
+}
+
+/// An IEEE 802.3 CRC-32 calculation, which can be fed its data in chunks.
+///
+/// This computes [`ieee_crc32()`][ieee_crc32] incrementally, as [`Crc`][crc] does the CRC-24.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::crc::IeeeCrc32;
+/// let mut crc = IeeeCrc32::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.checksum(), 0xCBF43926);
+/// ```
+///
+/// [ieee_crc32]: fn.ieee_crc32.html
+/// [crc]: struct.Crc.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IeeeCrc32(Crc<32>);
+
+impl IeeeCrc32 {
+    /// Creates a new CRC-32 calculation over no data.
+    pub fn new() -> IeeeCrc32 {
+        IeeeCrc32(Crc::with_polynomial_and_init(0x04C1_1DB7, 0xFFFF_FFFF))
+    }
+
+    /// Passes the given data through the CRC.
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            // Reflected input: each byte is fed least significant bit first.
+            self.0.update(&[byte.reverse_bits()]);
+        }
+    }
+
+    /// Returns the CRC-32 of the data passed through so far.
+    pub fn checksum(&self) -> u32 {
+        !self.0.checksum().reverse_bits()
+    }
+}
+
+impl Default for IeeeCrc32 {
+    fn default() -> IeeeCrc32 {
+        IeeeCrc32::new()
+    }
+}
+
+/// A flash writer which passes everything written through it to the CRC-24.
+///
+/// Each write is forwarded to the inner writer and its data passed through a [`Crc`][crc], so
+/// that an image can be checksummed as it is written, without reading it back. The writes must
+/// be made in order, with the image's contents only: the sum covers the data in the order in
+/// which it was written, regardless of the addresses.
+///
+/// [crc]: struct.Crc.html
+pub struct CrcWriter<W: FlashWriter> {
+    inner: W,
+    crc: Crc,
+}
+
+impl<W: FlashWriter> CrcWriter<W> {
+    /// Creates a writer which forwards its writes to `inner`.
+    pub fn new(inner: W) -> CrcWriter<W> {
+        CrcWriter {
+            inner,
+            crc: Crc::new(),
+        }
+    }
+
+    /// Returns the sum of the data written so far (see [`calculate()`][calculate]).
+    ///
+    /// [calculate]: fn.calculate.html
+    pub fn checksum(&self) -> u32 {
+        self.crc.checksum()
+    }
+
+    /// Returns the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: FlashWriter> FlashWriter for CrcWriter<W> {
+    fn write(&mut self, addr: u32, data: &[u8]) {
+        self.crc.update(data);
+        self.inner.write(addr, data)
+    }
+}
+
+/// A means of computing the checksum of an image.
+///
+/// This allows the CRC to be offloaded to a hardware peripheral (see
+/// `Image::verify_bootable_using()`), or replaced by a mock in tests of the selection logic. An
+/// engine must produce the same sum as [`calculate()`][calculate]. It is only given images whose
+/// checksum uses that CRC; images recording another algorithm (see `Image::crc_algo()`) are
+/// summed in software regardless of the engine supplied.
+///
+/// [calculate]: fn.calculate.html
+pub trait CrcEngine {
+    /// Computes the sum of the given data.
+    fn compute(&mut self, data: &[u8]) -> u32;
+}
+
+/// The CRC engine implemented in software, used by `Image::verify_bootable()`.
+///
+/// The data is passed through a [`Crc`][crc] in chunks of
+/// [`VERIFY_CHUNK_BYTES`][chunk], which suits flash that is read through a small window.
+///
+/// [crc]: struct.Crc.html
+/// [chunk]: ../constant.VERIFY_CHUNK_BYTES.html
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SoftwareCrc;
+
+impl CrcEngine for SoftwareCrc {
+    fn compute(&mut self, data: &[u8]) -> u32 {
+        let mut crc = Crc::new();
+        for chunk in data.chunks(VERIFY_CHUNK_BYTES) {
+            crc.update(chunk);
+        }
+        crc.checksum()
+    }
+}
+
+/// Calculates the sum of data passed through the 24-bit CRC. The sum will reside in the least
+/// significant bytes of the returned `u32`.
+fn crc<'a, T: Iterator<Item = &'a u8>>(data: T) -> u32 {
+    let mut crc = Crc::new();
+    for byte in data {
+        crc.push(*byte);
+    }
+    crc.remainder()
 }
 
 #[cfg(test)]
@@ -181,12 +661,300 @@ mod test {
         assert_eq!(is_valid(&[1, 2, 3, 4, 0x62, 0x9D, 0x29]), true);
         assert_eq!(is_valid(&[1, 2, 3, 5, 0x62, 0x9D, 0x29]), false);
     }
+
+    #[test]
+    fn test_is_valid_short() {
+        for data in &[&[0][..], &[0, 0], &[1], &[0, 1], &[0x5D, 0x6D]] {
+            assert_eq!(is_valid(data), super::crc(data.iter()) == 0, "{:?}", data);
+        }
+        assert!(is_valid(&[0, 0]));
+        assert!(!is_valid(&[0, 1]));
+    }
+
+    #[test]
+    fn test_crc_width_bytes() {
+        assert_eq!(CRC_WIDTH_BYTES, 3);
+        assert!(CRC_POLYNOMIAL >> (8 * CRC_WIDTH_BYTES) == 0);
+
+        // The sum of a message is the remainder of the message followed by the appended zeros.
+        let mut data = [0x41, 0x42, 0x43, 0x44, 0, 0, 0];
+        let (message, zeros) = data.split_at(data.len() - CRC_WIDTH_BYTES);
+        assert_eq!(zeros, [0; CRC_WIDTH_BYTES]);
+        assert_eq!(calculate(message), super::crc(message.iter().chain(zeros)));
+
+        // And that sum occupies exactly the width of the CRC.
+        let (len, sum) = (message.len(), calculate(message));
+        for (i, byte) in data[len..].iter_mut().enumerate() {
+            *byte = (sum >> (8 * (CRC_WIDTH_BYTES - 1 - i))) as u8;
+        }
+        assert!(is_valid(&data));
+    }
+
+    #[test]
+    fn test_check() {
+        let data = [1, 2, 3, 4, 0x62, 0x9D, 0x29];
+        assert_eq!(check(&data), (true, 0x629D29));
+
+        let mut corrupt = data;
+        corrupt[0] = 0;
+        assert_eq!(check(&corrupt), (false, calculate(&corrupt[..4])));
+        let mut corrupt = data;
+        corrupt[6] = 0x28;
+        assert_eq!(check(&corrupt), (false, 0x629D29));
+
+        for data in &[
+            &[][..],
+            &[0],
+            &[0, 1],
+            &[0x5D, 0x6D, 0xCB],
+            &[1, 0x5D, 0x6D, 0xCB],
+        ] {
+            let split = data.len().saturating_sub(CRC_WIDTH_BYTES);
+            assert_eq!(check(data), (is_valid(data), calculate(&data[..split])));
+        }
+    }
+
+    #[test]
+    fn test_is_valid_any() {
+        // The sum of the data under the CRC-24/OPENPGP polynomial.
+        let openpgp = 0x0086_4CFB;
+        let mut crc = Crc::<24>::with_polynomial(openpgp);
+        crc.update(&[0x41, 0x42, 0x43, 0x44]);
+        let sum = crc.checksum();
+        let data = [
+            0x41,
+            0x42,
+            0x43,
+            0x44,
+            (sum >> 16) as u8,
+            (sum >> 8) as u8,
+            sum as u8,
+        ];
+
+        assert!(!is_valid(&data));
+        assert!(!is_valid_any(&data, &[CRC_POLYNOMIAL]));
+        assert!(is_valid_any(&data, &[CRC_POLYNOMIAL, openpgp]));
+        assert!(is_valid_any(&data, &[openpgp, CRC_POLYNOMIAL]));
+        assert!(!is_valid_any(&data, &[]));
+        assert!(is_valid_any(
+            &[0x41, 0x42, 0x43, 0x44, 0xA7, 0x62, 0x9E],
+            &[CRC_POLYNOMIAL]
+        ));
+    }
+
+    #[test]
+    fn test_self_test() {
+        assert!(self_test());
+    }
+
+    #[test]
+    fn test_calculate_words() {
+        assert_eq!(calculate_words(&[]), calculate(&[]));
+        assert_eq!(
+            calculate_words(&[0x44434241]),
+            calculate(&[0x41, 0x42, 0x43, 0x44])
+        );
+        assert_eq!(
+            calculate_words(&[0x44434241, 0x04030201]),
+            calculate(&[0x41, 0x42, 0x43, 0x44, 1, 2, 3, 4])
+        );
+        assert_ne!(
+            calculate_words(&[0x41424344]),
+            calculate(&[0x41, 0x42, 0x43, 0x44])
+        );
+    }
+
+    #[test]
+    fn test_calculate_segments() {
+        let data = [0x41, 0x42, 0x43, 0x44];
+        assert_eq!(calculate_segments(&[]), calculate(&[]));
+        assert_eq!(calculate_segments(&[&[]]), calculate(&[]));
+        assert_eq!(calculate_segments(&[&data]), 0xA7629E);
+        for i in 0..data.len() + 1 {
+            let (head, tail) = data.split_at(i);
+            assert_eq!(calculate_segments(&[head, tail]), 0xA7629E);
+            assert_eq!(calculate_segments(&[head, &[], tail]), 0xA7629E);
+        }
+        assert_eq!(
+            calculate_segments(&[&data[..1], &data[1..2], &data[2..3], &data[3..]]),
+            0xA7629E
+        );
+    }
+
+    #[test]
+    fn test_is_valid_segments() {
+        let data = [0x41, 0x42, 0x43, 0x44, 0xA7, 0x62, 0x9E];
+        assert!(is_valid_segments(&[]));
+        for i in 0..data.len() + 1 {
+            for j in i..data.len() + 1 {
+                assert!(is_valid_segments(&[&data[..i], &data[i..j], &data[j..]]));
+            }
+        }
+        assert!(!is_valid_segments(&[&data[..4], &data[5..]]));
+        assert!(!is_valid_segments(&[&data[..3], &[0x45], &data[4..]]));
+    }
+
+    #[test]
+    fn test_is_valid_stream() {
+        let data = [0x41, 0x42, 0x43, 0x44];
+        assert!(is_valid_stream(None.into_iter(), 0));
+        for size in 1..data.len() + 1 {
+            assert!(is_valid_stream(data.chunks(size), 0xA7629E));
+            assert!(!is_valid_stream(data.chunks(size), 0xA7629F));
+        }
+        assert!(!is_valid_stream(data[..3].chunks(2), 0xA7629E));
+    }
+
+    #[test]
+    fn test_ieee_crc32() {
+        assert_eq!(ieee_crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(ieee_crc32(b""), 0);
+        assert_eq!(
+            ieee_crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414FA339
+        );
+    }
+
+    #[test]
+    fn test_ct_eq() {
+        assert!(ct_eq(0, 0));
+        assert!(ct_eq(!0, !0));
+        assert!(ct_eq(0xA7629E, calculate(b"ABCD")));
+        assert!(!ct_eq(0, !0));
+        for bit in 0..32 {
+            assert!(!ct_eq(0xA7629E, 0xA7629E ^ 1 << bit));
+        }
+    }
+
+    #[test]
+    fn test_ccitt16() {
+        assert_eq!(ccitt16(b"123456789"), 0x29B1);
+        assert_eq!(ccitt16(b""), 0xFFFF);
+        assert_eq!(ccitt16(b"A"), 0xB915);
+    }
+
+    #[test]
+    fn test_crc_with_init() {
+        let data = b"123456789";
+        assert_eq!(Crc::with_init(0), Crc::new());
+
+        let mut crc = Crc::with_init(0);
+        crc.update(&[0x41, 0x42, 0x43, 0x44]);
+        assert_eq!(crc.checksum(), calculate(&[0x41, 0x42, 0x43, 0x44]));
+
+        // CRC-24/FLEXRAY-A and CRC-24/FLEXRAY-B
+        for &(init, check) in &[(0xFEDCBA, 0x7979BD), (0xABCDEF, 0x1F23B8)] {
+            let mut crc = Crc::with_init(init);
+            assert_eq!(crc.checksum(), init);
+            crc.update(data);
+            assert_eq!(crc.checksum(), check);
+            crc.update(&[(check >> 16) as u8, (check >> 8) as u8, check as u8]);
+            assert_eq!(crc.remainder(), 0);
+        }
+    }
+
+    #[test]
+    fn test_crc_generic() {
+        let data = b"123456789";
+
+        // The default width is the CRC-24 used by the rest of the crate.
+        let mut crc = Crc::<24>::with_polynomial(CRC_POLYNOMIAL);
+        assert_eq!(crc, Crc::new());
+        crc.update(&[0x41, 0x42, 0x43, 0x44]);
+        assert_eq!(crc.checksum(), calculate(&[0x41, 0x42, 0x43, 0x44]));
+        assert_eq!(
+            Crc::<24>::with_polynomial(!0),
+            Crc::with_polynomial(0xFF_FFFF)
+        );
+
+        // CRC-32/MPEG-2
+        let mut crc = Crc::<32>::with_polynomial_and_init(0x04C1_1DB7, 0xFFFF_FFFF);
+        crc.update(data);
+        assert_eq!(crc.checksum(), 0x0376_E6E7);
+        let sum = crc.checksum();
+        crc.update(&[
+            (sum >> 24) as u8,
+            (sum >> 16) as u8,
+            (sum >> 8) as u8,
+            sum as u8,
+        ]);
+        assert_eq!(crc.remainder(), 0);
+
+        // CRC-32/CKSUM
+        let mut crc = Crc::<32>::with_polynomial(0x04C1_1DB7);
+        crc.update(data);
+        assert_eq!(!crc.checksum(), 0x765E_7680);
+
+        // CRC-7/MMC and CRC-15/CAN
+        let mut crc = Crc::<7>::with_polynomial(0x09);
+        crc.update(data);
+        assert_eq!(crc.checksum(), 0x75);
+        let mut crc = Crc::<15>::with_polynomial(0x4599);
+        crc.update(data);
+        assert_eq!(crc.checksum(), 0x059E);
+
+        // CRC-5/EPC-C1G2
+        let mut crc = Crc::<5>::with_polynomial_and_init(0x09, 0x09);
+        assert_eq!(crc.checksum(), 0x09);
+        crc.update(data);
+        assert_eq!(crc.checksum(), 0x00);
+    }
+
+    #[test]
+    fn test_crc() {
+        let mut crc = Crc::new();
+        assert_eq!(crc.remainder(), 0);
+        assert_eq!(crc.checksum(), 0);
+
+        crc.update(&[0x41, 0x42]);
+        crc.update(&[0x43, 0x44]);
+        assert_eq!(crc.checksum(), 0xA7629E);
+        assert_eq!(crc.remainder(), super::crc([0x41, 0x42, 0x43, 0x44].iter()));
+
+        crc.update(&[0xA7, 0x62, 0x9E]);
+        assert_eq!(crc.remainder(), 0);
+    }
+
+    #[test]
+    fn test_software_crc() {
+        let mut engine = SoftwareCrc;
+        assert_eq!(engine.compute(&[]), calculate(&[]));
+        assert_eq!(engine.compute(b"ABCD"), calculate(b"ABCD"));
+    }
+
+    #[test]
+    fn test_crc_writer() {
+        #[derive(Default)]
+        struct Recorder {
+            writes: usize,
+            bytes: usize,
+        }
+
+        impl FlashWriter for Recorder {
+            fn write(&mut self, _: u32, data: &[u8]) {
+                self.writes += 1;
+                self.bytes += data.len();
+            }
+        }
+
+        let mut writer = CrcWriter::new(Recorder::default());
+        assert_eq!(writer.checksum(), calculate(&[]));
+        writer.write(0x1000, b"ABCD");
+        assert_eq!(writer.checksum(), calculate(b"ABCD"));
+
+        let mut writer = CrcWriter::new(Recorder::default());
+        writer.write(0x1000, b"AB");
+        writer.write(0x1002, b"CD");
+        assert_eq!(writer.checksum(), calculate(b"ABCD"));
+        let recorder = writer.into_inner();
+        assert_eq!((recorder.writes, recorder.bytes), (2, 4));
+    }
 }
 
 #[cfg(test)]
 mod bench {
     use super::*;
-    use test::Bencher;
+    use test::{black_box, Bencher};
 
     #[bench]
     fn bench_calculate(b: &mut Bencher) {
@@ -201,4 +969,10 @@ mod bench {
         data[10_002] = 0xFEu8;
         b.iter(|| is_valid(&data));
     }
+
+    #[bench]
+    fn bench_is_valid_short(b: &mut Bencher) {
+        let data = [0xA5u8; CRC_WIDTH_BYTES - 1];
+        b.iter(|| is_valid(black_box(&data)));
+    }
 }
@@ -0,0 +1,55 @@
+// Copyright 2015 Alex Crawford
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistence of footer changes to flash.
+
+/// A means of writing to flash.
+///
+/// Implementations are responsible for whatever unlocking, erasing, and programming is required
+/// by the underlying flash controller.
+pub trait FlashWriter {
+    /// Writes the given data to flash, starting at the given address.
+    fn write(&mut self, addr: u32, data: &[u8]);
+}
+
+/// The granularity, in bytes, with which [`write_changes()`][write_changes] programs flash.
+///
+/// [write_changes]: fn.write_changes.html
+pub const WORD_SIZE: usize = 4;
+
+/// Writes only the words of `new` which differ from `old`, the data currently in flash at the
+/// given address.
+///
+/// Programming NOR flash can only clear bits; setting one requires the page to be erased. This
+/// returns false, without writing anything, if any bit set in `new` is clear in `old`, leaving
+/// the caller to erase and rewrite the page. Words which are unchanged aren't written at all,
+/// sparing the flash's endurance.
+///
+/// # Panics
+///
+/// Panics if `old` and `new` differ in length.
+pub fn write_changes<W: FlashWriter>(writer: &mut W, addr: u32, old: &[u8], new: &[u8]) -> bool {
+    assert_eq!(old.len(), new.len(), "old and new data differ in length");
+    if old.iter().zip(new).any(|(old, new)| new & !old != 0) {
+        return false;
+    }
+
+    let words = old.chunks(WORD_SIZE).zip(new.chunks(WORD_SIZE));
+    for (i, (old, new)) in words.enumerate() {
+        if old != new {
+            writer.write(addr + (i * WORD_SIZE) as u32, new);
+        }
+    }
+    true
+}
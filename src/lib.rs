@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-#![feature(asm, test)]
+#![feature(test)]
 #![no_std]
 
 //! Framework for dynamically choosing between bootable images.
@@ -27,7 +27,8 @@
 //! (typically two) and the newer, working image of the two will be booted. In
 //! order to be considered for boot, the image must have a valid checksum, it must
 //! not be marked as having failed to boot, and it must have at least one available
-//! boot attempt (images can be given, at most, four attempts).  When an image is
+//! boot attempt (images are given four attempts by default, and can be given up to
+//! eight if they have an extension; see [`Image`][image]).  When an image is
 //! booted, the number of available attempts is decremented if it hasn't been
 //! marked as having previously successfully booted. Once booted, images must mark
 //! themselves as having successfully booted. Otherwise, the next time through the
@@ -44,7 +45,47 @@
 //! image plus its checksum as one block of data through the [CRC][crc]. If the
 //! image is valid, the result of the CRC will be 0.
 //!
+//! The footer may be followed by an optional [`Extension`][extension], two words which
+//! record the rest of the image's status: the markers set while booting it, its extra
+//! boot attempts, the upper bits of its length and checksum, its CRC algorithm, the
+//! hardware and bootloader it requires, and the CRC sealing its footer. The extension may in
+//! turn be followed by the image's [`BuildInfo`][build_info], which is recorded when
+//! the image is flashed and never written by the bootloader. An image without them
+//! is described by its footer alone.
+//!
+//! The footer has no version field of its own; the layout has instead been revised by
+//! giving meaning to bits of the extension which an erased extension leaves set. An
+//! image relying on a revision must only be flashed alongside a bootloader which
+//! understands it:
+//!
+//! - Images of 16 MiB or more record the upper bits of their length in the extension.
+//!   An older bootloader sees only the footer's 24 bits, so it would locate the image
+//!   wrongly and mark it invalid.
+//! - The extension grew from one word to two, moving the build information to 24
+//!   bytes past the footer.
+//! - The CRC sealing the footer also covers the extension's fields recorded when the
+//!   image is flashed, so an image sealed for an older bootloader fails verification
+//!   with this one and must be reflashed.
+//!
+//! [image]: struct.Image.html
 //! [crc]: https://en.wikipedia.org/wiki/Cyclic_redundancy_check
+//! [extension]: struct.Extension.html
+//! [build_info]: struct.BuildInfo.html
+//!
+//! # Fatal Errors ##
+//!
+//! Should one of the framework's internal invariants be violated (e.g. an image
+//! whose start address precedes the beginning of the address space reaching the
+//! boot path), it panics by default. With the `fatal-hook` feature enabled, it
+//! instead calls a function provided by the integrator, which typically waits
+//! for interrupts or resets the processor:
+//!
+//! ```rust,ignore
+//! #[no_mangle]
+//! pub fn switcher_on_fatal() -> ! {
+//!     cortex_m::peripheral::SCB::sys_reset()
+//! }
+//! ```
 //!
 //! # Examples
 //!
@@ -66,196 +107,6806 @@
 
 #[macro_use]
 extern crate bitfield;
+#[cfg(any(test, feature = "sim"))]
+#[macro_use]
+extern crate std;
+#[cfg(feature = "sha256")]
+extern crate sha2;
 #[cfg(test)]
 extern crate test;
 
 pub mod crc;
+pub mod flash;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
-use core::cmp::Ordering;
-use core::slice;
+use bitfield::BitRange;
+#[cfg(not(feature = "sim"))]
+use core::arch::asm;
+use core::cmp::{self, Ordering};
+use core::convert::TryFrom;
+use core::{fmt, mem, ptr, slice};
+use crc::{Crc, CrcEngine, IeeeCrc32, SoftwareCrc};
+use flash::FlashWriter;
 
 /// A bootable image.
 ///
 /// Images can be created from an address using [`Image::from()`][from].
 ///
+/// `MAX_ATTEMPTS` is the number of boot attempts a trial image is given, which allows products
+/// to choose their own retry budget. The footer records [`Footer::MAX_ATTEMPTS`][max] attempts
+/// (the default budget) and an extension records a further
+/// [`Extension::EXTRA_ATTEMPTS`][extra], so budgets of up to [`RECORDABLE_ATTEMPTS`][recordable]
+/// are supported and a larger one fails to compile. An image without an extension can't record
+/// more than its footer does, so its budget is capped at `Footer::MAX_ATTEMPTS`. A smaller
+/// budget is obtained by treating the surplus attempts as already consumed (see
+/// [`attempts()`][attempts]). Images are created with the default budget; use
+/// [`with_max_attempts()`][with_max_attempts] to choose another.
+///
 /// [from]: #method.from
-pub struct Image<'a> {
+/// [max]: struct.Footer.html#associatedconstant.MAX_ATTEMPTS
+/// [extra]: struct.Extension.html#associatedconstant.EXTRA_ATTEMPTS
+/// [recordable]: #associatedconstant.RECORDABLE_ATTEMPTS
+/// [attempts]: #method.attempts
+/// [with_max_attempts]: #method.with_max_attempts
+pub struct Image<'a, const MAX_ATTEMPTS: u8 = 4> {
     footer: &'a mut Footer,
+    extension: Option<&'a mut Extension>,
+    build_info: Option<&'a BuildInfo>,
+    // The length over which the checksum was last computed this session, and the result.
+    cached_crc: Option<(u32, Option<u32>)>,
+    index: u8,
 }
 
 impl<'a> Image<'a> {
     /// Creates an image from the given address.
     ///
-    /// This address must point to the beginning of a valid footer (the last word in an image).
-    /// This method is unsafe due to the fact that no verification is performed on the address.
+    /// # Safety
+    ///
+    /// No verification is performed on the address. It must point to the beginning of a valid
+    /// footer (the last word in an image), which must not be accessed by other means for the
+    /// lifetime of the image. A null address is fatal.
     pub unsafe fn from(addr: u32) -> Image<'a> {
         Image {
-            footer: (addr as *mut Footer).as_mut().unwrap(),
+            footer: (addr as *mut Footer)
+                .as_mut()
+                .unwrap_or_else(|| fatal("footer address is null")),
+            extension: None,
+            build_info: None,
+            cached_crc: None,
+            index: 0,
         }
     }
 
-    /// Determines if the image can be booted.
+    /// Creates an image from the given footer.
     ///
-    /// If the image has not been marked as having succeeded or failed to boot, its checksum will
-    /// be verified and the validity recorded.
-    pub fn verify_bootable(&mut self) -> bool {
-        if self.footer.success() {
-            return true;
+    /// This is intended for footers which have already been located (e.g. by a partition table),
+    /// sparing the round trip through an address. The image's contents are still found relative
+    /// to the footer: they are the [`length()`][length] bytes immediately preceding it.
+    ///
+    /// # Safety
+    ///
+    /// Verifying the image reads the memory preceding the footer, so the footer must be
+    /// immediately preceded by the image it describes (or be erased).
+    ///
+    /// [length]: #method.length
+    pub unsafe fn from_footer(footer: &'a mut Footer) -> Image<'a> {
+        Image {
+            footer,
+            extension: None,
+            build_info: None,
+            cached_crc: None,
+            index: 0,
         }
+    }
 
-        if self.footer.failure() || self.footer.invalid() {
-            return false;
+    /// Creates an image from the given address, identified by the given partition index.
+    ///
+    /// The index is purely for the caller's benefit (e.g. to identify the image in logs or
+    /// telemetry) and has no effect on how the image is verified or selected. Images created by
+    /// any of the other constructors have an index of zero.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for [`from()`][from] apply to the address.
+    ///
+    /// [from]: #method.from
+    pub unsafe fn from_indexed(addr: u32, index: u8) -> Image<'a> {
+        Image {
+            index,
+            ..Image::from(addr)
         }
+    }
 
-        if !self.footer.valid() {
-            if crc::is_valid(unsafe {
-                slice::from_raw_parts(
-                    match self.footer.start_address() {
-                        Some(addr) => addr as *const u8,
-                        None => return false,
-                    },
-                    self.footer.length() as usize,
-                )
-            }) {
-                self.footer.set_valid()
-            } else {
-                self.footer.set_invalid();
-                return false;
-            }
+    /// Creates an image, with an extension, from the given address.
+    ///
+    /// # Safety
+    ///
+    /// No verification is performed on the address. It must point to the beginning of a valid
+    /// footer, which must be immediately followed by an [`Extension`][extension], and neither may
+    /// be accessed by other means for the lifetime of the image. A null address is fatal.
+    ///
+    /// [extension]: struct.Extension.html
+    pub unsafe fn from_extended(addr: u32) -> Image<'a> {
+        let footer = addr as *mut Footer;
+        Image {
+            footer: footer
+                .as_mut()
+                .unwrap_or_else(|| fatal("footer address is null")),
+            extension: (footer.offset(1) as *mut Extension).as_mut(),
+            build_info: None,
+            cached_crc: None,
+            index: 0,
         }
+    }
 
-        self.footer.attempts() > 0
+    /// Creates an image, with an extension and build information, from the given address.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for [`from_extended()`][from_extended] apply, and the extension
+    /// must in turn be immediately followed by a [`BuildInfo`][build_info], which the image
+    /// borrows for its lifetime.
+    ///
+    /// [build_info]: struct.BuildInfo.html
+    /// [from_extended]: #method.from_extended
+    pub unsafe fn from_extended_with_build_info(addr: u32) -> Image<'a> {
+        let build_info = addr as usize + mem::size_of::<Footer>() + mem::size_of::<Extension>();
+        Image {
+            build_info: (build_info as *const BuildInfo).as_ref(),
+            ..Image::from_extended(addr)
+        }
     }
 
-    /// Boots the image.
+    /// Creates an image from the given buffer, whose last eight bytes hold the footer.
     ///
-    /// If the image has not been marked as having successfully booted, the number of remaining
-    /// boot attempts is decremented.
-    pub fn boot(&mut self) -> ! {
-        if !self.footer.success() {
-            self.footer.decrement_attempts();
+    /// The image's contents are the [`length()`][length] bytes immediately preceding the footer,
+    /// so the buffer must end with the image and its footer, exactly as they would be laid out in
+    /// flash. This allows images to be constructed and verified on the host.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than a footer, if the footer isn't aligned to eight bytes,
+    /// or if the length recorded in the (non-erased) footer exceeds the rest of the buffer. The
+    /// fallible equivalent is `Image::try_from()`.
+    ///
+    /// [length]: struct.Footer.html#method.length
+    pub fn from_slice(buf: &'a mut [u8]) -> Image<'a> {
+        match Image::try_from(buf) {
+            Ok(image) => image,
+            Err(err) => panic!("{}", err),
         }
+    }
 
-        unsafe {
-            // Set the stack pointer to 0.
-            // Set the instruction pointer to the beginning of the image.
-            // TODO: Move the ISV to the beginning of the image.
-            asm!(
-                "mov sp, $0;
-                 mov ip, $1;"
-                :
-                :
-                "r" (0),
-                "r" (self.footer.start_address().unwrap())
-            );
+    /// Returns the image with a budget of `N` boot attempts, rather than the default.
+    ///
+    /// The budget can't exceed what the footer and extension can record:
+    ///
+    /// ```compile_fail
+    /// # use switcher::Image;
+    /// # let mut buf = [0xFF; 8];
+    /// let image = Image::from_slice(&mut buf).with_max_attempts::<9>();
+    /// image.attempts();
+    /// ```
+    pub fn with_max_attempts<const N: u8>(self) -> Image<'a, N> {
+        Image {
+            footer: self.footer,
+            extension: self.extension,
+            build_info: self.build_info,
+            cached_crc: self.cached_crc,
+            index: self.index,
         }
-        unreachable!()
     }
 }
 
-impl<'a> Ord for Image<'a> {
-    fn cmp(&self, other: &Image) -> Ordering {
-        self.footer.version().cmp(&other.footer.version())
+impl<'a, const MAX_ATTEMPTS: u8> Image<'a, MAX_ATTEMPTS> {
+    /// The largest budget an image can be given: the attempts recorded by the footer and by an
+    /// extension together.
+    pub const RECORDABLE_ATTEMPTS: usize = Footer::MAX_ATTEMPTS + Extension::EXTRA_ATTEMPTS;
+    // The number of recordable attempts beyond the image's budget. Evaluating this fails to
+    // compile if the budget exceeds what can be recorded.
+    const UNBUDGETED_ATTEMPTS: usize = Self::RECORDABLE_ATTEMPTS - MAX_ATTEMPTS as usize;
+
+    /// Returns the number of remaining boot attempts, out of `MAX_ATTEMPTS`.
+    ///
+    /// This is the number of attempts recorded in the footer (see
+    /// [`Footer::attempts()`][attempts]) and the extension, if any (see
+    /// [`Extension::extra_attempts()`][extra_attempts]), less those beyond the image's budget. A
+    /// confirmed image always reports its full budget.
+    ///
+    /// [attempts]: struct.Footer.html#method.attempts
+    /// [extra_attempts]: struct.Extension.html#method.extra_attempts
+    pub fn attempts(&self) -> usize {
+        let unrecorded = match self.extension {
+            Some(_) => 0,
+            None => Extension::EXTRA_ATTEMPTS,
+        };
+        let recorded = match self.extension {
+            // The success marker implies a refill, so that confirming an image only clears bits.
+            _ if self.footer.success() => Self::RECORDABLE_ATTEMPTS - unrecorded,
+            Some(ref extension) => self.footer.attempts() + extension.extra_attempts(),
+            None => self.footer.attempts(),
+        };
+        recorded.saturating_sub(Self::UNBUDGETED_ATTEMPTS.saturating_sub(unrecorded))
     }
-}
 
-impl<'a> PartialOrd for Image<'a> {
-    fn partial_cmp(&self, other: &Image) -> Option<Ordering> {
-        Some(self.cmp(other))
+    /// Consumes one of the image's remaining boot attempts and returns the number left.
+    ///
+    /// Once the attempts have been exhausted, this has no effect and returns zero. Unlike
+    /// [`prepare_boot()`][prepare_boot], this pays no heed to the image's status: the attempt is
+    /// consumed even if the image has been confirmed or is a recovery image, though a confirmed
+    /// image goes on reporting its full budget.
+    ///
+    /// [prepare_boot]: #method.prepare_boot
+    pub fn consume_attempt(&mut self) -> usize {
+        self.decrement_attempts();
+        self.attempts()
     }
-}
 
-impl<'a> Eq for Image<'a> {}
+    // The footer's attempts are consumed first, so that images with the default budget only ever
+    // touch the footer. The extension's are only consumed while they're within the budget.
+    fn decrement_attempts(&mut self) {
+        if self.footer.attempts() > 0 {
+            self.footer.decrement_attempts()
+        } else if self.attempts() > 0 {
+            if let Some(ref mut extension) = self.extension {
+                extension.decrement_extra_attempts()
+            }
+        }
+    }
 
-impl<'a> PartialEq for Image<'a> {
-    fn eq(&self, other: &Image) -> bool {
-        self.footer.version() == other.footer.version()
+    /// Returns true if the image is compatible with the given board revision.
+    ///
+    /// An image is compatible if its extension requires the given revision or
+    /// [`HW_REV_ANY`][any]. Images without an extension are compatible with any hardware.
+    ///
+    /// [any]: constant.HW_REV_ANY.html
+    pub fn supports_hardware(&self, hw_rev: u8) -> bool {
+        match self.extension {
+            Some(ref extension) => extension.hw_rev() == HW_REV_ANY || extension.hw_rev() == hw_rev,
+            None => true,
+        }
     }
-}
 
-/// Returns the newer, bootable image of the given images.
-///
-/// This first determines which of the images are bootable, updating the image validity if
-/// necessary (see [`Image::verify_bootable()`][verify_bootable] for details). If both image are
-/// bootable, the newer of the two is returned. Otherwise, the only bootable image is returned, if
-/// any.
-///
-/// [verify_bootable]: struct.Image.html#method.verify_bootable
-pub fn select<'a, 'b>(
-    image_a: &'a mut Image<'b>,
-    image_b: &'a mut Image<'b>,
-) -> Option<&'a mut Image<'b>> {
-    match (image_a.verify_bootable(), image_b.verify_bootable()) {
-        (true, true) => {
-            if image_a > image_b {
-                Some(image_a)
-            } else {
-                Some(image_b)
+    /// Returns true if the image is a recovery image.
+    ///
+    /// A recovery image is a last resort: the selections never prefer it over a bootable image
+    /// which isn't one, and booting it never consumes its attempts, so that it remains bootable
+    /// for as long as its contents are intact. Images without an extension are never recovery
+    /// images (see [`Extension::recovery()`][recovery]).
+    ///
+    /// [recovery]: struct.Extension.html#method.recovery
+    pub fn is_recovery(&self) -> bool {
+        matches!(self.extension, Some(ref extension) if extension.recovery())
+    }
+
+    /// Uses up the image's grace boot, returning true if one had been granted.
+    fn take_grace(&mut self) -> bool {
+        match self.extension {
+            Some(ref mut extension) if extension.grace() => {
+                extension.use_grace();
+                true
             }
+            _ => false,
         }
-        (true, false) => Some(image_a),
-        (false, true) => Some(image_b),
-        (false, false) => None,
     }
-}
 
-bitfield!{
-    /// The footer for a bootable image.
+    /// Returns the CRC algorithm over which the image's checksum was computed.
     ///
-    /// This struct should be initialized with 1s except for the length, checksum, and version when
-    /// it is flashed. The struct must also follow the image it describes such that the checksum
-    /// immediately follows the image.
-    pub struct Footer(u32);
+    /// This is [`CRC_ALGO_SWITCHER`][switcher] unless the image's extension records another (see
+    /// [`Extension::crc_algo()`][crc_algo]). The switcher CRC is recorded in the footer alone;
+    /// the CRC-32 of [`CRC_ALGO_IEEE`][ieee] needs the extension's upper eight bits as well (see
+    /// [`Extension::checksum_hi()`][checksum_hi]). An image recording an algorithm which this
+    /// crate doesn't know is treated as having an invalid checksum.
+    ///
+    /// [switcher]: constant.CRC_ALGO_SWITCHER.html
+    /// [crc_algo]: struct.Extension.html#method.crc_algo
+    /// [ieee]: constant.CRC_ALGO_IEEE.html
+    /// [checksum_hi]: struct.Extension.html#method.checksum_hi
+    pub fn crc_algo(&self) -> u8 {
+        self.extension
+            .as_ref()
+            .map_or(CRC_ALGO_SWITCHER, |extension| extension.crc_algo())
+    }
 
-    /// Returns the checksum of the image.
-    pub checksum, _: 23, 0;
-    /// Returns the version of the image.
-    pub version, _: 31, 24;
-    /// Returns the length (in bytes) of the image.
-    pub length, _: 55, 32;
-    n_valid, set_n_valid: 56;
-    n_invalid, set_n_invalid: 57;
-    n_success, set_n_success: 58;
-    n_failure, set_n_failure: 59;
-    n_attempts, set_n_attempts: 63, 63;
-}
+    /// Returns true if the image can be started by the given version of the bootloader.
+    ///
+    /// An image is compatible unless its extension requires a newer bootloader (see
+    /// [`Extension::min_bootloader()`][min_bootloader]). Images without an extension are
+    /// compatible with any bootloader.
+    ///
+    /// [min_bootloader]: struct.Extension.html#method.min_bootloader
+    pub fn supports_bootloader(&self, bootloader_version: u8) -> bool {
+        match self.extension {
+            Some(ref extension) => extension.min_bootloader() <= bootloader_version,
+            None => true,
+        }
+    }
 
-impl Footer {
-    /// Returns true if the image has been marked valid.
-    pub fn valid(&self) -> bool {
-        !self.n_valid()
+    /// Returns the time at which the image was built, as a Unix timestamp, or zero if unknown.
+    ///
+    /// The build time is unknown unless the image was created with its build information (see
+    /// [`from_extended_with_build_info()`][from_build_info]) and that was recorded when the image
+    /// was flashed.
+    ///
+    /// [from_build_info]: #method.from_extended_with_build_info
+    pub fn build_time(&self) -> u32 {
+        self.build_info.map_or(0, |info| info.build_time())
     }
-    /// Marks the image as being valid.
-    pub fn set_valid(&mut self) {
-        self.set_n_valid(false)
+
+    /// Returns true if the image ends with a SHA-256 digest of the rest of its contents.
+    ///
+    /// The digest is recorded in the last [`DIGEST_BYTES`][digest_bytes] bytes of the image, where
+    /// it is also covered by the image's checksum, and its presence is recorded in the image's
+    /// build information (see [`BuildInfo::has_digest()`][has_digest]). Images created without
+    /// their build information never have a digest.
+    ///
+    /// [digest_bytes]: constant.DIGEST_BYTES.html
+    /// [has_digest]: struct.BuildInfo.html#method.has_digest
+    pub fn has_digest(&self) -> bool {
+        matches!(self.build_info, Some(info) if info.has_digest())
     }
-    /// Returns true if the image has been marked invalid.
-    pub fn invalid(&self) -> bool {
-        !self.n_invalid()
+
+    /// Returns true if the image's SHA-256 digest matches its contents.
+    ///
+    /// This is far slower than verifying the checksum, which [`verify_bootable()`][verify_bootable]
+    /// continues to do on every boot; it is meant to be run periodically (e.g. after an update or
+    /// at a maintenance interval) to catch corruption which the CRC could miss. The footer isn't
+    /// modified. Returns false if the image has no digest (see [`has_digest()`][has_digest]) or
+    /// is too short to hold one.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    /// [has_digest]: #method.has_digest
+    #[cfg(feature = "sha256")]
+    pub fn deep_verify(&self) -> bool {
+        use sha2::{Digest, Sha256};
+
+        let contents = match self.contents() {
+            Some(contents) if self.has_digest() && contents.len() >= DIGEST_BYTES => contents,
+            _ => return false,
+        };
+        let (hashed, stored) = contents.split_at(contents.len() - DIGEST_BYTES);
+        let diff = Sha256::digest(hashed)
+            .iter()
+            .zip(stored)
+            .fold(0, |diff, (a, b)| diff | u32::from(a ^ b));
+        crc::ct_eq(diff, 0)
     }
-    /// Marks the image as being invalid.
-    pub fn set_invalid(&mut self) {
-        self.set_n_invalid(false)
+
+    /// Returns the partition index of the image (see [`from_indexed()`][from_indexed]).
+    ///
+    /// [from_indexed]: #method.from_indexed
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the footer of the image.
+    pub fn footer(&self) -> &Footer {
+        self.footer
     }
+
     /// Returns true if the image has been marked as having successfully booted.
-    pub fn success(&self) -> bool {
-        !self.n_success()
+    pub fn is_confirmed(&self) -> bool {
+        self.footer.success()
     }
+
     /// Returns true if the image has been marked as having failed to boot.
-    pub fn failure(&self) -> bool {
-        !self.n_failure()
+    ///
+    /// A failure which has been forgiven (see [`Extension::forgiven()`][forgiven]) is disregarded.
+    ///
+    /// [forgiven]: struct.Extension.html#method.forgiven
+    pub fn is_failed(&self) -> bool {
+        self.footer.failure()
+            && !matches!(self.extension, Some(ref extension) if extension.forgiven())
     }
-    /// Returns the number of remaining boot attempts.
-    pub fn attempts(&self) -> usize {
-        self.n_attempts() as usize
+
+    /// Returns true if the image has boot attempts remaining and hasn't been marked as failed.
+    ///
+    /// Unlike [`is_bootable()`][is_bootable], this ignores the checksum entirely, so it is cheap
+    /// enough to decide whether an image is worth verifying at all.
+    ///
+    /// [is_bootable]: #method.is_bootable
+    pub fn can_retry(&self) -> bool {
+        self.attempts() > 0 && !self.is_failed()
     }
-    /// Decrements the number of remaining boot attempts by one.
-    pub fn decrement_attempts(&mut self) {
-        let attempts = self.n_attempts() << 1;
-        self.set_n_attempts(attempts)
+
+    /// Returns the address of the image's footer.
+    ///
+    /// This is the address the image was created from and is where updated status bits need to be
+    /// written when persisting changes to the footer.
+    pub fn footer_address(&self) -> u32 {
+        self.footer as *const Footer as u32
     }
-    /// Returns the address to the start of the image.
-    pub fn start_address(&self) -> Option<u32> {
-        (self as *const Footer as u32).checked_sub(self.length())
+
+    /// Returns the number of times the image's footer has been persisted.
+    ///
+    /// Every call to [`persist()`][persist] counts as a write, which can be used to estimate the
+    /// wear on the flash page holding the footer. The count saturates at
+    /// [`Extension::MAX_WRITE_COUNT`][max]. An image
+    /// without an extension does not track its writes and always returns zero.
+    ///
+    /// [persist]: #method.persist
+    /// [max]: struct.Extension.html#associatedconstant.MAX_WRITE_COUNT
+    pub fn footer_write_count(&self) -> u32 {
+        self.extension
+            .as_ref()
+            .map_or(0, |extension| extension.write_count())
+    }
+
+    /// Writes the image's footer, and its extension if it has one, to flash.
+    ///
+    /// If the image has an extension, its write count is incremented before it is written.
+    pub fn persist<W: FlashWriter>(&mut self, writer: &mut W) {
+        let addr = self.footer_address();
+        writer.write(addr, bytes(self.footer));
+        if let Some(ref mut extension) = self.extension {
+            extension.increment_write_count();
+            writer.write(addr + mem::size_of::<Footer>() as u32, bytes(*extension));
+        }
+    }
+
+    /// Writes the changes made to the image's footer, and its extension if it has one, since
+    /// they held `prior` and `prior_extension`.
+    ///
+    /// Unlike [`persist()`][persist], only the words which changed are written (see
+    /// [`flash::write_changes()`][write_changes]) and the write count is left alone, since
+    /// incrementing it sets bits. Every transition made while selecting and booting an image
+    /// only clears bits (see [`Footer::only_clears()`][only_clears]): verifying it, consuming its
+    /// attempts or its grace boot, recording that it is booting or has started, consuming its
+    /// swap request, forgiving its failure, sealing its footer, and marking it confirmed,
+    /// invalid, or failed. Those changes can therefore be programmed without erasing the page.
+    /// The updater's changes, such as [`finish_write()`][finish_write] and
+    /// [`Footer::reset_status()`][reset_status], set bits and need an erase.
+    ///
+    /// Returns false, without writing anything, if either the footer or the extension has had a
+    /// bit set (or if `prior_extension` doesn't match whether the image has an extension); the
+    /// caller must then erase the page and use [`persist()`][persist] instead.
+    ///
+    /// [persist]: #method.persist
+    /// [write_changes]: flash/fn.write_changes.html
+    /// [only_clears]: struct.Footer.html#method.only_clears
+    /// [finish_write]: #method.finish_write
+    /// [reset_status]: struct.Footer.html#method.reset_status
+    pub fn persist_changes<W: FlashWriter>(
+        &self,
+        writer: &mut W,
+        prior: &Footer,
+        prior_extension: Option<&Extension>,
+    ) -> bool {
+        let clears_only = self.footer.only_clears(prior)
+            && match (&self.extension, prior_extension) {
+                (Some(extension), Some(prior)) => extension.only_clears(prior),
+                (None, None) => true,
+                _ => false,
+            };
+        if !clears_only {
+            return false;
+        }
+
+        let addr = self.footer_address();
+        flash::write_changes(writer, addr, bytes(prior), bytes(self.footer));
+        if let (Some(extension), Some(prior)) = (&self.extension, prior_extension) {
+            let addr = addr + mem::size_of::<Footer>() as u32;
+            flash::write_changes(writer, addr, bytes(prior), bytes(*extension));
+        }
+        true
+    }
+
+    /// Copies the image, along with its footer and extension, to the flash whose footer is at
+    /// `footer_address`, writing through the given writer.
+    ///
+    /// This is intended for mirroring an image to the other bank of a dual-bank part. Nothing in
+    /// the footer or extension depends on where the image resides and the checksum covers only
+    /// the image's contents, so the copy verifies exactly as the original does. The copy's footer
+    /// is written with its status reset (see [`Footer::reset_status()`][reset_status]), so that
+    /// the copy is only trusted once its own checksum has been verified. Likewise, the extension
+    /// keeps only the fields recorded when the image was flashed and the CRC sealing its footer,
+    /// its status being reset (see [`Extension::reset_status()`][reset_extension]), so that the
+    /// copy doesn't inherit the original's boot history. The contents are written first
+    /// and the footer after them, so an interrupted copy is left without a valid footer. Returns
+    /// false, without writing anything, if either the image or its copy would begin before the
+    /// start of the address space. The destination must be erased beforehand.
+    ///
+    /// [reset_status]: struct.Footer.html#method.reset_status
+    /// [reset_extension]: struct.Extension.html#method.reset_status
+    pub fn copy_to<W: FlashWriter>(&self, writer: &mut W, footer_address: u32) -> bool {
+        let (contents, start) = match (self.contents(), footer_address.checked_sub(self.length())) {
+            (Some(contents), Some(start)) => (contents, start),
+            _ => return false,
+        };
+
+        let mut footer = Footer(self.footer.0);
+        footer.reset_status();
+        writer.write(start, contents);
+        writer.write(footer_address, bytes(&footer));
+        if let Some(ref extension) = self.extension {
+            let mut extension = Extension(extension.0);
+            extension.reset_status();
+            writer.write(
+                footer_address + mem::size_of::<Footer>() as u32,
+                bytes(&extension),
+            );
+        }
+        true
+    }
+
+    /// Determines if the image can be booted.
+    ///
+    /// An erased footer (see [`Footer::is_erased()`][is_erased]) describes an empty slot and an
+    /// image which is being written (see [`begin_write()`][begin_write]) may be incomplete; both
+    /// are rejected immediately, without being marked. Otherwise, the footer is checked against
+    /// its CRC, if one was recorded (see [`seal_footer()`][seal_footer]), and for structural
+    /// soundness (see [`Footer::sane()`][sane]); a corrupt or unsound footer is marked invalid
+    /// without going any further. A footer marked both valid and invalid (see
+    /// [`Footer::is_corrupt()`][is_corrupt]) is rejected next, taking precedence over every
+    /// other marker. If the image has not been
+    /// marked as having succeeded or failed to boot, its checksum will then be verified, using
+    /// the image's CRC algorithm (see [`crc_algo()`][crc_algo]), and the validity recorded.
+    ///
+    /// [is_erased]: struct.Footer.html#method.is_erased
+    /// [begin_write]: #method.begin_write
+    /// [seal_footer]: #method.seal_footer
+    /// [sane]: struct.Footer.html#method.sane
+    /// [is_corrupt]: struct.Footer.html#method.is_corrupt
+    /// [crc_algo]: #method.crc_algo
+    pub fn verify_bootable(&mut self) -> bool {
+        self.verify_bootable_reporting(|_, _| ())
+    }
+
+    /// Determines if the image can be booted, reporting a checksum mismatch.
+    ///
+    /// This is identical to [`verify_bootable()`][verify_bootable], except that `on_invalid` is
+    /// called with the computed and stored checksums (in that order) when the image's checksum
+    /// is found not to match, before the image is marked invalid. This gives the caller a chance
+    /// to record the corruption (e.g. by capturing the image for later upload) before the slot is
+    /// overwritten. The callback is not invoked if the mismatch was already recorded.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    pub fn verify_bootable_reporting<F>(&mut self, on_invalid: F) -> bool
+    where
+        F: FnMut(u32, u32),
+    {
+        self.verify_using(&mut SoftwareCrc, on_invalid)
+    }
+
+    /// Determines if the image can be booted, given a copy of its contents.
+    ///
+    /// This is identical to [`verify_bootable()`][verify_bootable], except that the checksum is
+    /// computed over `image` rather than over the flash preceding the footer. It is intended for
+    /// parts whose flash isn't memory-mapped and has to be read into RAM first (e.g. through a
+    /// QSPI controller). The caller must supply exactly the [`length()`][length] bytes of the
+    /// image; anything else will fail the checksum and the image will be marked invalid.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    /// [length]: #method.length
+    pub fn verify_bootable_with(&mut self, image: &[u8]) -> bool {
+        self.verify(|this| this.checksum_of(image), |_, _| ())
+    }
+
+    /// Determines if the image can be booted, computing its checksum with the given engine.
+    ///
+    /// This is identical to [`verify_bootable()`][verify_bootable], except that the checksum is
+    /// computed by `engine` (e.g. a CRC peripheral) over the image's contents. The engine only
+    /// computes the switcher CRC, so the checksum of an image using another algorithm (see
+    /// [`crc_algo()`][crc_algo]) is computed in software instead.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    /// [crc_algo]: #method.crc_algo
+    pub fn verify_bootable_using<E: CrcEngine>(&mut self, engine: &mut E) -> bool {
+        self.verify_using(engine, |_, _| ())
+    }
+
+    /// Verifies the image, computing its checksum over its contents with the given engine.
+    fn verify_using<E, F>(&mut self, engine: &mut E, on_invalid: F) -> bool
+    where
+        E: CrcEngine,
+        F: FnMut(u32, u32),
+    {
+        self.verify(
+            |this| {
+                this.contents()
+                    .and_then(|contents| this.checksum_using(contents, engine))
+            },
+            on_invalid,
+        )
+    }
+
+    /// Returns true if verifying the image requires its checksum to be computed.
+    ///
+    /// This is false if the outcome of [`verify_bootable()`][verify_bootable] can be determined
+    /// from the footer alone (or from an earlier verification this session), in which case there
+    /// is no need to read the image before calling [`finish_verify()`][finish_verify].
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    /// [finish_verify]: #method.finish_verify
+    pub fn needs_checksum(&self) -> bool {
+        if self.footer.is_erased() || self.is_writing() {
+            return false;
+        }
+
+        if !self.verify_self() {
+            return false;
+        }
+
+        let marked = self.footer.success()
+            || self.is_failed()
+            || self.footer.invalid()
+            || self.footer.valid();
+        !marked && self.cached_result().is_none()
+    }
+
+    /// Completes the verification of an image whose contents were passed through `crc` by the
+    /// caller.
+    ///
+    /// This is the second phase of [`verify_bootable()`][verify_bootable], for platforms on which
+    /// the image has to be read through a controller (possibly asynchronously) rather than
+    /// directly from memory. The caller feeds the image's contents (the
+    /// [`length()`][length] bytes starting at [`start_address()`][start_address]) through
+    /// [`Crc::update()`][update] as it reads them, chunk by chunk, and the status of the image
+    /// is then determined and recorded exactly as by `verify_bootable()`. The CRC is only
+    /// consulted if [`needs_checksum()`][needs_checksum] is true; otherwise, reading the image can
+    /// be skipped entirely. `crc` only computes the switcher CRC, so an image using another
+    /// algorithm (see [`crc_algo()`][crc_algo]) can't be verified this way: if its checksum is
+    /// needed, false is returned and nothing is recorded in the footer.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    /// [length]: struct.Footer.html#method.length
+    /// [start_address]: struct.Footer.html#method.start_address
+    /// [update]: crc/struct.Crc.html#method.update
+    /// [needs_checksum]: #method.needs_checksum
+    /// [crc_algo]: #method.crc_algo
+    pub fn finish_verify(&mut self, crc: &Crc) -> bool {
+        if self.needs_checksum() && self.crc_algo() != CRC_ALGO_SWITCHER {
+            return false;
+        }
+        self.verify(|_| Some(crc.checksum()), |_, _| ())
+    }
+
+    /// Determines if the image can be booted, giving the caller a chance to cancel between chunks.
+    ///
+    /// This is identical to [`verify_bootable()`][verify_bootable], except that the image's
+    /// contents are passed through the CRC `chunk_size` bytes at a time and `should_continue` is
+    /// polled before each chunk. If it returns false, the verification is abandoned and `None` is
+    /// returned; nothing is recorded in the footer, so a later verification starts afresh. This
+    /// allows a higher-priority event to preempt the verification of a large image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    pub fn verify_bootable_cancellable<F>(
+        &mut self,
+        chunk_size: usize,
+        mut should_continue: F,
+    ) -> Option<bool>
+    where
+        F: FnMut() -> bool,
+    {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+
+        let algo = self.crc_algo();
+        let mut crc = Crc::new();
+        let mut ieee = IeeeCrc32::new();
+        if self.needs_checksum() {
+            if let Some(contents) = self.contents() {
+                for chunk in contents.chunks(chunk_size) {
+                    if !should_continue() {
+                        return None;
+                    }
+                    match algo {
+                        CRC_ALGO_SWITCHER => crc.update(chunk),
+                        CRC_ALGO_IEEE => ieee.update(chunk),
+                        _ => (),
+                    }
+                }
+            }
+        }
+        Some(self.verify(
+            |_| match algo {
+                CRC_ALGO_SWITCHER => Some(crc.checksum()),
+                CRC_ALGO_IEEE => Some(ieee.checksum()),
+                _ => None,
+            },
+            |_, _| (),
+        ))
+    }
+
+    /// Returns the boot status of an image which has just been verified.
+    ///
+    /// An image which is being written is reported as erased, since its slot doesn't yet hold a
+    /// complete image. A checksum mismatch is only distinguished from an earlier invalidation if
+    /// it was found during this session.
+    fn verified_status(&self) -> BootStatus {
+        if self.footer.is_erased() || self.is_writing() {
+            BootStatus::Erased
+        } else if !self.verify_self() {
+            BootStatus::Unsound
+        } else if self.footer.is_corrupt() {
+            BootStatus::Corrupt
+        } else if self.footer.success() {
+            BootStatus::Confirmed
+        } else if self.is_failed() {
+            BootStatus::Failed
+        } else if self.footer.invalid() {
+            match self.cached_result() {
+                Some(false) => BootStatus::ChecksumInvalid,
+                _ => BootStatus::Invalid,
+            }
+        } else if self.attempts() > 0 {
+            BootStatus::Trial
+        } else {
+            BootStatus::Exhausted
+        }
+    }
+
+    /// Determines if the image can be booted, using `compute` to compute its checksum if needed.
+    fn verify<C, F>(&mut self, compute: C, mut on_invalid: F) -> bool
+    where
+        C: FnOnce(&Self) -> Option<u32>,
+        F: FnMut(u32, u32),
+    {
+        if self.footer.is_erased() || self.is_writing() {
+            return false;
+        }
+
+        if !self.verify_self() {
+            self.footer.set_invalid();
+            return false;
+        }
+
+        if self.footer.is_corrupt() {
+            return false;
+        }
+
+        if self.footer.success() {
+            return true;
+        }
+
+        if self.is_failed() || self.footer.invalid() {
+            return false;
+        }
+
+        if !self.footer.valid() {
+            let valid = match self.cached_result() {
+                Some(valid) => valid,
+                None => {
+                    let computed = compute(self);
+                    self.cached_crc = Some((self.length(), computed));
+                    match computed {
+                        Some(computed) if computed == self.stored_checksum() => true,
+                        Some(computed) => {
+                            on_invalid(computed, self.stored_checksum());
+                            false
+                        }
+                        None => false,
+                    }
+                }
+            };
+
+            if valid {
+                self.footer.set_valid()
+            } else {
+                self.footer.set_invalid();
+                return false;
+            }
+        }
+
+        self.attempts() > 0
+    }
+
+    /// Records the CRC of the footer's metadata in the image's extension.
+    ///
+    /// Once sealed, [`verify_bootable()`][verify_bootable] rejects the image if its footer no
+    /// longer matches the recorded CRC (see [`Footer::metadata_crc()`][metadata_crc]). The CRC
+    /// also covers the extension's fields which are recorded when the image is flashed (its
+    /// recovery marker, CRC algorithm, grace, upper length and checksum bits, and required
+    /// hardware and bootloader), since these locate and verify the image just as the footer
+    /// does; its markers are excluded like the footer's. Returns false if the image has no
+    /// extension in which to record the CRC.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    /// [metadata_crc]: struct.Footer.html#method.metadata_crc
+    pub fn seal_footer(&mut self) -> bool {
+        let crc = self.sealed_crc();
+        match self.extension {
+            Some(ref mut extension) => {
+                extension.set_footer_crc(crc);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the CRC recorded by [`seal_footer()`][seal_footer]: that of the footer's metadata,
+    /// followed by the fields of the extension (if any) which are recorded when it's flashed.
+    ///
+    /// [seal_footer]: #method.seal_footer
+    fn sealed_crc(&self) -> u16 {
+        let mut crc = Crc::<16>::with_polynomial(crc::CRC16_POLYNOMIAL);
+        crc.update(&self.footer.0.to_le_bytes()[..7]);
+        if let Some(ref extension) = self.extension {
+            let fields = u64::from(extension.flashed_fields())
+                | u64::from(extension.raw_checksum_hi()) << 28;
+            crc.update(&fields.to_le_bytes()[..5]);
+        }
+        crc.checksum() as u16
+    }
+
+    /// Returns true if the footer itself is intact, without reading any of the image.
+    ///
+    /// The footer's metadata must match the CRC recorded by [`seal_footer()`][seal_footer], if
+    /// any, and the footer must be structurally sound (see [`Footer::sane()`][sane]). This only
+    /// reads the footer and extension, so it is cheap enough to check before trusting the length
+    /// to locate the image; [`verify_bootable()`][verify_bootable] does so first. Note that the
+    /// CRC lives in the extension, so a footer without one can only be checked for soundness.
+    ///
+    /// [seal_footer]: #method.seal_footer
+    /// [sane]: struct.Footer.html#method.sane
+    /// [verify_bootable]: #method.verify_bootable
+    pub fn verify_self(&self) -> bool {
+        self.footer_crc_valid() && self.sane()
+    }
+
+    /// Returns false if the footer's metadata doesn't match the CRC recorded by
+    /// [`seal_footer()`][seal_footer]. Footers without a recorded CRC are always accepted.
+    ///
+    /// [seal_footer]: #method.seal_footer
+    fn footer_crc_valid(&self) -> bool {
+        match self
+            .extension
+            .as_ref()
+            .and_then(|extension| extension.footer_crc())
+        {
+            Some(crc) => crc == self.sealed_crc(),
+            None => true,
+        }
+    }
+
+    /// Determines if the image can be booted, without modifying its footer.
+    ///
+    /// This performs the same checks as [`verify_bootable()`][verify_bootable] but never records
+    /// the outcome, so an image which hasn't yet been marked valid has its checksum verified on
+    /// every call.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    pub fn is_bootable(&self) -> bool {
+        if self.footer.is_erased() || self.is_writing() {
+            return false;
+        }
+
+        if !self.verify_self() || self.footer.is_corrupt() {
+            return false;
+        }
+
+        if self.footer.success() {
+            return true;
+        }
+
+        if self.is_failed() || self.footer.invalid() {
+            return false;
+        }
+
+        let valid = self.footer.valid()
+            || self
+                .cached_result()
+                .unwrap_or_else(|| self.checksum_valid());
+        valid && self.attempts() > 0
+    }
+
+    /// Returns the result of the checksum verification performed during this session, if any.
+    ///
+    /// The first time [`verify_bootable()`][verify_bootable] has to compute the checksum, the
+    /// computed CRC and the length it covered are remembered in RAM for the lifetime of this
+    /// `Image`. Later calls use them instead of recomputing the CRC, even if the valid/invalid bits
+    /// recorded in the footer haven't (yet) been written to flash. The cache only applies while
+    /// the image's length is unchanged and can be dropped with
+    /// [`invalidate_cache()`][invalidate_cache] (e.g. after the image has been rewritten). It is
+    /// never persisted.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    /// [invalidate_cache]: #method.invalidate_cache
+    pub fn cached_result(&self) -> Option<bool> {
+        match self.cached_crc {
+            Some((length, computed)) if length == self.length() => {
+                Some(computed == Some(self.stored_checksum()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Discards the checksum computed during this session, so that the next verification
+    /// recomputes it (see [`cached_result()`][cached_result]).
+    ///
+    /// [cached_result]: #method.cached_result
+    pub fn invalidate_cache(&mut self) {
+        self.cached_crc = None
+    }
+
+    /// Resynchronizes the image with flash after its footer or contents were changed by other
+    /// means (e.g. by an interrupt handler or DMA).
+    ///
+    /// The footer and extension are read through their references, so they always reflect flash;
+    /// what this discards is everything the image remembers in RAM, which is currently only the
+    /// checksum cached during verification (see [`cached_result()`][cached_result]). The next
+    /// verification therefore reflects the flash as it is now.
+    ///
+    /// [cached_result]: #method.cached_result
+    pub fn refresh(&mut self) {
+        self.invalidate_cache()
+    }
+
+    /// Returns the approximate number of cycles needed to verify the image's checksum.
+    ///
+    /// This is the number of bytes passed through the CRC (the image plus the appended zeros)
+    /// multiplied by [`crc::CYCLES_PER_BYTE`][cycles], saturating at `u32::MAX`. It is a
+    /// heuristic, intended for sizing a conservative watchdog window around
+    /// [`verify_bootable()`][verify_bootable], rather than an exact timing.
+    ///
+    /// [cycles]: crc/constant.CYCLES_PER_BYTE.html
+    /// [verify_bootable]: #method.verify_bootable
+    pub fn estimated_crc_cycles(&self) -> u32 {
+        self.length()
+            .saturating_add(crc::CRC_WIDTH_BYTES as u32)
+            .saturating_mul(crc::CYCLES_PER_BYTE)
+    }
+
+    /// Returns the length (in bytes) of the image.
+    ///
+    /// The footer records the low 24 bits of the length. Images of 16 MiB or more also need an
+    /// extension, which records the upper bits (see [`Extension::length_hi()`][length_hi]).
+    ///
+    /// [length_hi]: struct.Extension.html#method.length_hi
+    pub fn length(&self) -> u32 {
+        let hi = self
+            .extension
+            .as_ref()
+            .map_or(0, |extension| extension.length_hi());
+        u32::from(hi) << 24 | self.footer.length()
+    }
+
+    /// Returns the number of bytes of flash occupied by the image and its footer.
+    ///
+    /// An extension or build information following the footer isn't included. The result
+    /// saturates at `u32::MAX`, which no sound image reaches.
+    pub fn total_size(&self) -> u32 {
+        self.length().saturating_add(Footer::FOOTER_SIZE as u32)
+    }
+
+    /// Returns the address to the start of the image.
+    ///
+    /// Unlike [`Footer::start_address()`][start_address], this accounts for the upper bits of
+    /// the length recorded in the image's extension.
+    ///
+    /// [start_address]: struct.Footer.html#method.start_address
+    pub fn start_address(&self) -> Option<u32> {
+        self.footer_address().checked_sub(self.length())
+    }
+
+    /// Returns true if the image's footer is structurally sound (see
+    /// [`Footer::sane()`][sane]), taking the full length of the image and any forgiven failure
+    /// into account.
+    ///
+    /// [sane]: struct.Footer.html#method.sane
+    fn sane(&self) -> bool {
+        self.length() != 0
+            && self.start_address().is_some()
+            && !(self.footer.success() && self.is_failed())
+    }
+
+    /// Returns true if the image's reset vector points into the image.
+    ///
+    /// The image is expected to begin with its vector table, the second word of which is the
+    /// reset vector. This must be a Thumb address (i.e. have its least significant bit set)
+    /// between the start of the image and its footer. A reset vector failing this is a sign of a
+    /// corrupt or unprogrammed image, possibly one which the checksum has missed. This is cheap
+    /// compared to [`verify_bootable()`][verify_bootable], which doesn't perform the check; a
+    /// cautious caller can check both before booting.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    pub fn reset_vector_plausible(&self) -> bool {
+        match (self.reset_vector(), self.start_address()) {
+            (Some(vector), Some(start)) => {
+                let target = vector & !1;
+                vector & 1 == 1 && start <= target && target < self.footer_address()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the image's reset vector: the second word of the vector table at its start.
+    ///
+    /// The value is returned as stored, including the Thumb bit. This is `None` if the image's
+    /// start address is invalid or the image is too short to hold the word.
+    pub fn reset_vector(&self) -> Option<u32> {
+        self.start_address()?;
+        let contents = self.contents()?;
+        let mut word = [0; 4];
+        word.copy_from_slice(contents.get(4..8)?);
+        Some(u32::from_ne_bytes(word))
+    }
+
+    /// Returns true if the image is intact, regardless of its boot status.
+    ///
+    /// The image must have a sound footer and a valid checksum, but may have run out of attempts
+    /// or have been marked as failed. The footer isn't modified.
+    fn checksum_intact(&self) -> bool {
+        if self.footer.is_erased() || self.is_writing() {
+            return false;
+        }
+
+        if !self.verify_self() || self.footer.invalid() {
+            return false;
+        }
+
+        self.footer.valid()
+            || self
+                .cached_result()
+                .unwrap_or_else(|| self.checksum_valid())
+    }
+
+    /// Returns the checksum recorded in the footer alongside the one computed over the region it
+    /// describes.
+    ///
+    /// This is meant for diagnosing a corrupt image or re-checksumming one, and has no side
+    /// effects: the footer isn't marked and the cached result (see
+    /// [`invalidate_cache()`][invalidate_cache]) is neither consulted nor updated, so the CRC is
+    /// always computed afresh. Nothing is computed if the image would begin before the start of
+    /// the address space, in which case both `start` and `computed` are `None`.
+    ///
+    /// [invalidate_cache]: #method.invalidate_cache
+    pub fn verify_detail(&self) -> VerifyDetail {
+        let start = self.start_address();
+        VerifyDetail {
+            stored: self.stored_checksum(),
+            computed: start.and_then(|_| self.calculate_checksum()),
+            length: self.length(),
+            start,
+        }
+    }
+
+    /// Returns true if the image's checksum is valid.
+    fn checksum_valid(&self) -> bool {
+        self.calculate_checksum() == Some(self.stored_checksum())
+    }
+
+    /// Returns the checksum recorded for the image: the footer's, extended by the upper bits
+    /// recorded in the extension for a 32-bit algorithm (see [`crc_algo()`][crc_algo]).
+    ///
+    /// [crc_algo]: #method.crc_algo
+    fn stored_checksum(&self) -> u32 {
+        match self.extension {
+            Some(ref extension) if extension.crc_algo() == CRC_ALGO_IEEE => {
+                u32::from(extension.checksum_hi()) << 24 | self.footer.checksum()
+            }
+            _ => self.footer.checksum(),
+        }
+    }
+
+    /// Computes the checksum over the contents of the image in software (see
+    /// [`crc::SoftwareCrc`][software]).
+    ///
+    /// [software]: crc/struct.SoftwareCrc.html
+    fn calculate_checksum(&self) -> Option<u32> {
+        self.contents()
+            .and_then(|contents| self.checksum_of(contents))
+    }
+
+    /// Computes the checksum of the given contents in software using the image's CRC algorithm.
+    fn checksum_of(&self, contents: &[u8]) -> Option<u32> {
+        self.checksum_using(contents, &mut SoftwareCrc)
+    }
+
+    /// Computes the checksum of the given contents using the image's CRC algorithm, or returns
+    /// `None` if the algorithm is unknown. Only the switcher CRC is computed by the engine.
+    fn checksum_using<E: CrcEngine>(&self, contents: &[u8], engine: &mut E) -> Option<u32> {
+        match self.crc_algo() {
+            CRC_ALGO_SWITCHER => Some(engine.compute(contents)),
+            CRC_ALGO_IEEE => Some(crc::ieee_crc32(contents)),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of the image (i.e. the `length()` bytes preceding the footer).
+    fn contents(&self) -> Option<&[u8]> {
+        // This is computed with pointer-sized arithmetic, rather than with start_address(), so
+        // that images over host memory (see from_slice()) can be verified.
+        let length = self.length() as usize;
+        (self.footer as *const Footer as usize)
+            .checked_sub(length)
+            .map(|start| unsafe { slice::from_raw_parts(start as *const u8, length) })
+    }
+
+    /// Marks the image as having successfully booted.
+    ///
+    /// Once marked, [`boot()`][boot] no longer decrements the number of remaining attempts. The
+    /// image also reports its full budget of `MAX_ATTEMPTS` from then on (see
+    /// [`attempts()`][attempts]), so that, should it ever need to go through trial boots again
+    /// (e.g. after a rollback and re-promotion), it starts with a full budget rather than
+    /// whatever remained when it was confirmed. The refill is implied by the success marker, so
+    /// confirming an image only clears bits.
+    ///
+    /// [boot]: #method.boot
+    /// [attempts]: #method.attempts
+    pub fn mark_success(&mut self) {
+        self.footer.set_success();
+        self.mark_started()
+    }
+
+    /// Marks the image as invalid.
+    ///
+    /// This is intended for integrity checks performed outside of this crate (e.g. a signature
+    /// check). An invalid image has bad contents; subsequent calls to
+    /// [`verify_bootable()`][verify_bootable] will return false without recomputing the checksum.
+    /// Compare this to [`mark_failure()`][mark_failure], which indicates that the contents were
+    /// fine but the image failed to boot.
+    ///
+    /// [verify_bootable]: #method.verify_bootable
+    /// [mark_failure]: #method.mark_failure
+    pub fn mark_invalid(&mut self) {
+        self.footer.set_invalid()
+    }
+
+    /// Marks the image as having failed to boot.
+    ///
+    /// A failed image is never booted again, regardless of the validity of its contents or the
+    /// number of remaining attempts. An image which was booting is recorded as having started
+    /// (see [`mark_started()`][mark_started]). The failure of an image whose earlier failure was
+    /// forgiven can't be recorded again, so its attempts are exhausted instead.
+    ///
+    /// [mark_started]: #method.mark_started
+    pub fn mark_failure(&mut self) {
+        self.footer.set_failure();
+        if !self.is_failed() {
+            self.footer.set_n_attempts(0);
+            if let Some(ref mut extension) = self.extension {
+                extension.set_n_extra_attempts(0)
+            }
+        }
+        self.mark_started()
+    }
+
+    /// Prepares the image to be booted.
+    ///
+    /// If the image has not been marked as having successfully booted, the number of remaining
+    /// boot attempts is decremented, unless it is a recovery image (see
+    /// [`is_recovery()`][is_recovery]). The returned [`Handoff`][handoff] describes the state the
+    /// image should be started with.
+    ///
+    /// If the image was granted a grace boot (see [`Extension::grace()`][grace]), the grace is
+    /// used up in place of the attempt. This only clears a bit of the extension, which the caller
+    /// must write back before starting the image; as that rewrites a single word, a reset can't
+    /// leave the grace in place once it has been taken.
+    ///
+    /// [is_recovery]: #method.is_recovery
+    /// [handoff]: struct.Handoff.html
+    /// [grace]: struct.Extension.html#method.grace
+    pub fn prepare_boot(&mut self) -> Handoff {
+        if !self.footer.success() && !self.is_recovery() && !self.take_grace() {
+            self.consume_attempt();
+        }
+
+        Handoff {
+            sp: 0,
+            pc: self
+                .start_address()
+                .unwrap_or_else(|| fatal("image starts below address zero")),
+        }
+    }
+
+    /// Prepares the image to be booted optimistically.
+    ///
+    /// Rather than consuming a boot attempt up front, this sets the image's booting marker and
+    /// defers the decision until the next time through the boot process. If the image records
+    /// that it has started early in its initialization (see [`mark_started()`][mark_started]),
+    /// the attempt is never consumed and a reset before the image is able to confirm itself is
+    /// not held against it. If the image hasn't started by the next time it is booted, it never
+    /// reached that point and the attempt is consumed then.
+    ///
+    /// The markers only ever clear bits, so each image gets a single optimistic boot: once it
+    /// has started, later boots, until the footer and extension are erased and rewritten,
+    /// consume their attempts up front, exactly as [`prepare_boot()`][prepare_boot] does. The
+    /// states an image goes through are:
+    ///
+    /// ```text
+    ///           prepare_optimistic_boot()            mark_started()
+    ///   trial ----------------------------> booting ---------------> started
+    ///                                        |   ^
+    ///                                        |   | prepare_optimistic_boot()
+    ///                                        +---+ (attempts decremented)
+    /// ```
+    ///
+    /// [`mark_success()`][mark_success] moves the image from any of these states to confirmed,
+    /// after which no attempts are consumed. An image without an extension cannot record the
+    /// booting marker, so this is equivalent to `prepare_boot()`, as it is for a recovery image,
+    /// whose attempts are never consumed. A grace boot, if granted, is used up in place of the
+    /// first attempt which would otherwise be consumed.
+    ///
+    /// [mark_started]: #method.mark_started
+    /// [mark_success]: #method.mark_success
+    /// [prepare_boot]: #method.prepare_boot
+    pub fn prepare_optimistic_boot(&mut self) -> Handoff {
+        if !self.footer.success() && !self.is_recovery() {
+            if let Some(ref mut extension) = self.extension {
+                if !extension.booting() {
+                    extension.set_booting();
+                    return Handoff {
+                        sp: 0,
+                        pc: self
+                            .start_address()
+                            .unwrap_or_else(|| fatal("image starts below address zero")),
+                    };
+                }
+            }
+        }
+
+        self.prepare_boot()
+    }
+
+    /// Sets the image's booting marker.
+    ///
+    /// This is intended to be called immediately before jumping to the image. The image is
+    /// considered to be booting until it calls [`mark_started()`][mark_started],
+    /// [`mark_success()`][mark_success], or [`mark_failure()`][mark_failure], so finding it still
+    /// booting on the next boot (see [`was_booting()`][was_booting]) means that the image hung or
+    /// crashed early on. Returns false if the image has no extension in which to record the
+    /// marker, or if the image has already started since it was last marked: neither marker can
+    /// be set again without erasing the extension.
+    ///
+    /// [mark_started]: #method.mark_started
+    /// [mark_success]: #method.mark_success
+    /// [mark_failure]: #method.mark_failure
+    /// [was_booting]: #method.was_booting
+    pub fn mark_booting(&mut self) -> bool {
+        match self.extension {
+            Some(ref mut extension) if !extension.started() => {
+                extension.set_booting();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true if the image's booting marker is set and the image hasn't since started.
+    ///
+    /// Images without an extension never have the marker set.
+    pub fn was_booting(&self) -> bool {
+        matches!(self.extension, Some(ref extension) if extension.booting() && !extension.started())
+    }
+
+    /// Records that the image has started, ending its boot.
+    ///
+    /// This is intended to be called by an image early in its initialization, once it has gotten
+    /// far enough that a reset shouldn't count against it (see
+    /// [`prepare_optimistic_boot()`][prepare_optimistic_boot]). It sets a marker of its own,
+    /// rather than clearing the booting marker, so that it only clears a bit. This does not
+    /// confirm the image; it must still [`mark_success()`][mark_success] once it is known to be
+    /// working. Nothing is recorded unless the booting marker is set.
+    ///
+    /// [prepare_optimistic_boot]: #method.prepare_optimistic_boot
+    /// [mark_success]: #method.mark_success
+    pub fn mark_started(&mut self) {
+        match self.extension {
+            Some(ref mut extension) if extension.booting() => extension.set_started(),
+            _ => (),
+        }
+    }
+
+    /// Requests that the next selection prefer this image's sibling.
+    ///
+    /// This is a one-shot hint, recorded in the image's extension, which is consumed by the next
+    /// call to [`select()`][select]. Consuming it sets a marker of its own rather than clearing
+    /// the request, so an image can only request a swap once until its extension is erased.
+    /// Returns false if the image has no extension in which to record the request, or if its
+    /// request has already been consumed.
+    ///
+    /// [select]: fn.select.html
+    pub fn request_swap(&mut self) -> bool {
+        match self.extension {
+            Some(ref mut extension) if !extension.swap_taken() => {
+                extension.set_prefer_other();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Marks the image as being written, persisting the marker through the given writer.
+    ///
+    /// An updater must call this before it erases or writes any of the image's bytes, so that a
+    /// partially written image is never considered bootable should power be lost part way
+    /// through. The marker is written to flash on its own, before this returns. Returns false,
+    /// without writing anything, if the image has no extension in which to record the marker.
+    pub fn begin_write<W: FlashWriter>(&mut self, writer: &mut W) -> bool {
+        match self.extension {
+            Some(ref mut extension) => extension.set_writing(),
+            None => return false,
+        }
+        self.persist(writer);
+        true
+    }
+
+    /// Clears the image's writing marker, persisting the change through the given writer.
+    ///
+    /// This must only be called once the image, including its checksum, has been written in
+    /// full.
+    pub fn finish_write<W: FlashWriter>(&mut self, writer: &mut W) {
+        if let Some(ref mut extension) = self.extension {
+            extension.clear_writing();
+            self.persist(writer)
+        }
+    }
+
+    /// Returns true if the image is in the process of being written.
+    pub fn is_writing(&self) -> bool {
+        matches!(self.extension, Some(ref extension) if extension.writing())
+    }
+
+    /// Consumes the image's swap request, returning true if one had been made and not yet
+    /// consumed.
+    fn take_swap_request(&mut self) -> bool {
+        match self.extension {
+            Some(ref mut extension) if extension.prefer_other() && !extension.swap_taken() => {
+                extension.set_swap_taken();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Boots the image.
+    ///
+    /// If the image has not been marked as having successfully booted, the number of remaining
+    /// boot attempts is decremented (see [`prepare_boot()`][prepare_boot]). Interrupts are
+    /// disabled before control is handed to the image; it is up to the image to re-enable them
+    /// once its vector table is in place.
+    ///
+    /// With the `sim` feature enabled, no jump is performed. Instead, the handoff is recorded
+    /// (see [`sim::take_handoff()`][take_handoff]) and the calling thread panics.
+    ///
+    /// [prepare_boot]: #method.prepare_boot
+    /// [take_handoff]: sim/fn.take_handoff.html
+    pub fn boot(&mut self) -> ! {
+        jump(self.prepare_boot())
+    }
+
+    /// Boots the image optimistically.
+    ///
+    /// This is identical to [`boot()`][boot] except that the boot attempt is recorded using the
+    /// booting marker (see [`prepare_optimistic_boot()`][prepare_optimistic_boot]).
+    ///
+    /// [boot]: #method.boot
+    /// [prepare_optimistic_boot]: #method.prepare_optimistic_boot
+    pub fn boot_optimistic(&mut self) -> ! {
+        jump(self.prepare_optimistic_boot())
+    }
+}
+
+impl<'a, const MAX_ATTEMPTS: u8> fmt::Debug for Image<'a, MAX_ATTEMPTS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("index", &self.index)
+            .field("address", &format_args!("{:#010x}", self.footer_address()))
+            .field("footer", &self.footer)
+            .field("extension", &self.extension)
+            .finish()
+    }
+}
+
+impl<'a, const MAX_ATTEMPTS: u8> Image<'a, MAX_ATTEMPTS> {
+    /// Returns true if this image is newer than the other (i.e. `self > other`).
+    ///
+    /// Images of the same version are neither newer nor older than one another.
+    pub fn is_newer_than(&self, other: &Image<MAX_ATTEMPTS>) -> bool {
+        self.cmp(other) == Ordering::Greater
+    }
+
+    /// Returns true if this image is older than the other (i.e. `self < other`).
+    pub fn is_older_than(&self, other: &Image<MAX_ATTEMPTS>) -> bool {
+        self.cmp(other) == Ordering::Less
+    }
+
+    /// Returns true if the other image has the same contents, according to its footer.
+    ///
+    /// Unlike the comparison operators, which only consider the version (so that two different
+    /// builds of the same version are equal), this compares the checksum, version, and length
+    /// which identify the image. An updater can use this to avoid staging an image which is
+    /// already present. The contents themselves aren't compared, nor is the images' status.
+    pub fn is_exact_duplicate_of(&self, other: &Image<MAX_ATTEMPTS>) -> bool {
+        self.identity() == other.identity()
+    }
+
+    /// Returns the fields of the footer which identify the image's contents.
+    ///
+    /// The identity is fixed when the image is flashed, whatever becomes of its status, so it can
+    /// be kept by a client to recognize the image later (see [`ImageIdentity`][identity]).
+    ///
+    /// [identity]: struct.ImageIdentity.html
+    pub fn identity(&self) -> ImageIdentity {
+        ImageIdentity {
+            version: self.footer.version(),
+            length: self.length(),
+            checksum: self.footer.checksum(),
+        }
+    }
+
+    /// Compares two images by version and then, for images of the same version, by build time.
+    ///
+    /// This is intended for use with [`select_by()`][select_by] when several builds may share a
+    /// version (e.g. on a hotfix branch). An image whose build time is unknown (see
+    /// [`build_time()`][build_time]) is older than every build of the same version which has one.
+    ///
+    /// [select_by]: fn.select_by.html
+    /// [build_time]: #method.build_time
+    pub fn cmp_build_time(&self, other: &Image<MAX_ATTEMPTS>) -> Ordering {
+        self.cmp(other)
+            .then(self.build_time().cmp(&other.build_time()))
+    }
+
+    /// Compares the versions of two images, tolerating a wrap of the 8-bit version.
+    ///
+    /// A version which is numerically lower than another, but which is within
+    /// [`VERSION_WRAP_WINDOW`][window] of it when counting past 255, is considered to have
+    /// wrapped and is therefore newer (e.g. 0 is newer than 255). All other versions compare
+    /// numerically, so a genuinely old image is not mistaken for a new one (e.g. 200 is newer
+    /// than 0). Note that this is not a total order, so the result of selecting among more than
+    /// two images whose versions span the wrap can depend on their order.
+    ///
+    /// [window]: constant.VERSION_WRAP_WINDOW.html
+    pub fn cmp_wrapping(&self, other: &Image<MAX_ATTEMPTS>) -> Ordering {
+        let (a, b) = (self.footer.version(), other.footer.version());
+        if a < b && a.wrapping_sub(b) <= VERSION_WRAP_WINDOW {
+            Ordering::Greater
+        } else if b < a && b.wrapping_sub(a) <= VERSION_WRAP_WINDOW {
+            Ordering::Less
+        } else {
+            a.cmp(&b)
+        }
+    }
+}
+
+/// The board revision, recorded in an image's extension, which denotes compatibility with any
+/// hardware.
+///
+/// This is the erased state of the field, so images are compatible with any hardware unless
+/// their extension says otherwise.
+pub const HW_REV_ANY: u8 = 0xFF;
+
+/// The CRC algorithm, recorded in an image's extension, of the 24-bit CRC used throughout this
+/// crate (see [`crc::calculate()`][calculate]).
+///
+/// This is the erased state of the field, so images use it unless their extension says
+/// otherwise.
+///
+/// [calculate]: crc/fn.calculate.html
+pub const CRC_ALGO_SWITCHER: u8 = 0;
+
+/// The CRC algorithm, recorded in an image's extension, of the IEEE 802.3 CRC-32 (see
+/// [`crc::ieee_crc32()`][ieee_crc32]).
+///
+/// The footer holds the low 24 bits of the CRC-32 and the extension the upper eight (see
+/// [`Extension::checksum_hi()`][checksum_hi]), so the full 32 bits are verified.
+///
+/// [ieee_crc32]: crc/fn.ieee_crc32.html
+/// [checksum_hi]: struct.Extension.html#method.checksum_hi
+pub const CRC_ALGO_IEEE: u8 = 1;
+
+/// The largest distance, past 255, at which a lower version is considered to have wrapped.
+///
+/// See [`Image::cmp_wrapping()`][cmp_wrapping].
+///
+/// [cmp_wrapping]: struct.Image.html#method.cmp_wrapping
+pub const VERSION_WRAP_WINDOW: u8 = 16;
+
+/// The largest number of images considered by a selection over a slice of images.
+///
+/// Selecting from a longer slice is a bug, caught by a debug assertion; otherwise, the images
+/// beyond the first `MAX_IMAGES` are ignored. This applies to [`select_by()`][select_by] and the
+/// selections built upon it, as well as to [`select_verbose()`][select_verbose], whose statuses
+/// for any ignored images are those of unverified images.
+///
+/// [select_by]: fn.select_by.html
+/// [select_verbose]: fn.select_verbose.html
+pub const MAX_IMAGES: usize = 16;
+
+/// The number of bytes of an image read at a time while verifying its checksum.
+///
+/// [`Image::verify_bootable()`][verify_bootable] passes the image through the CRC one chunk at a
+/// time rather than reading it as a single region, which suits flash that is read through a small
+/// window.
+///
+/// [verify_bootable]: struct.Image.html#method.verify_bootable
+pub const VERIFY_CHUNK_BYTES: usize = 256;
+
+/// The size, in bytes, of the SHA-256 digest with which an image may end.
+///
+/// See [`Image::has_digest()`][has_digest].
+///
+/// [has_digest]: struct.Image.html#method.has_digest
+pub const DIGEST_BYTES: usize = 32;
+
+/// The initial processor state with which an image is started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Handoff {
+    /// The initial stack pointer.
+    pub sp: u32,
+    /// The initial instruction pointer.
+    pub pc: u32,
+}
+
+#[cfg(not(feature = "sim"))]
+fn jump(handoff: Handoff) -> ! {
+    unsafe {
+        // Disable interrupts. Until the image sets up its own vector table, any interrupt would
+        // be serviced by the bootloader's handlers.
+        #[cfg(target_arch = "arm")]
+        asm!("cpsid i", options(nomem, nostack, preserves_flags));
+
+        // Set the stack pointer.
+        // Set the instruction pointer to the beginning of the image.
+        // TODO: Move the ISV to the beginning of the image.
+        asm!(
+            "mov sp, {0}",
+            "mov ip, {1}",
+            in(reg) handoff.sp,
+            in(reg) handoff.pc,
+        );
+    }
+    fatal("returned from the jump to an image")
+}
+
+#[cfg(feature = "sim")]
+fn jump(handoff: Handoff) -> ! {
+    sim::disable_interrupts();
+    sim::record_handoff(handoff);
+    panic!("simulated boot to {:#010x}", handoff.pc)
+}
+
+#[cfg(not(feature = "sim"))]
+fn halt() -> ! {
+    loop {
+        #[cfg(target_arch = "arm")]
+        unsafe {
+            asm!("wfi", options(nomem, nostack, preserves_flags));
+        }
+        #[cfg(not(target_arch = "arm"))]
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(feature = "sim")]
+fn halt() -> ! {
+    sim::record_halt();
+    panic!("simulated halt")
+}
+
+/// A count of cold starts across all images, kept by the bootloader in a dedicated word of flash.
+///
+/// Unlike an image's boot attempts, this isn't tied to any one slot, so a bootloader can use it
+/// to detect a boot loop that spans several images (e.g. by entering recovery once the count
+/// passes a threshold). Each increment clears one more bit of the word, so the counter can be
+/// advanced without erasing flash; it saturates at [`MAX`][max] and is reset by erasing the
+/// word. Bounding the count to a window of time is up to the bootloader, which might erase the
+/// word once an image has been running for long enough.
+///
+/// [max]: #associatedconstant.MAX
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BootCounter(u32);
+
+impl BootCounter {
+    /// The largest count the counter can hold.
+    pub const MAX: u32 = 32;
+
+    /// Interprets the given word of flash as a boot counter.
+    pub fn from_bits(bits: u32) -> BootCounter {
+        BootCounter(bits)
+    }
+    /// Returns the raw value of the counter's word.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+    /// Returns the number of recorded boots.
+    pub fn count(&self) -> u32 {
+        self.0.count_zeros()
+    }
+    /// Records another boot and writes the counter, starting at the given address, through the
+    /// given writer.
+    ///
+    /// Once the counter has saturated, the write is skipped.
+    pub fn increment<W: FlashWriter>(&mut self, addr: u32, writer: &mut W) {
+        if self.0 != 0 {
+            // Clear the lowest bit which is still set.
+            self.0 &= self.0 - 1;
+            writer.write(addr, bytes(&self.0));
+        }
+    }
+}
+
+/// Returns the number of boots recorded by the counter at the given address.
+///
+/// See [`BootCounter`][counter] for details.
+///
+/// # Safety
+///
+/// The address must point to the word of flash which holds the counter.
+///
+/// [counter]: struct.BootCounter.html
+pub unsafe fn boot_counter(addr: u32) -> u32 {
+    BootCounter(ptr::read_volatile(addr as *const u32)).count()
+}
+
+/// Records a boot in the counter at the given address, writing it through the given writer, and
+/// returns the new count.
+///
+/// # Safety
+///
+/// The address must point to the word of flash which holds the counter.
+pub unsafe fn increment_boot_counter<W: FlashWriter>(addr: u32, writer: &mut W) -> u32 {
+    let mut counter = BootCounter(ptr::read_volatile(addr as *const u32));
+    counter.increment(addr, writer);
+    counter.count()
+}
+
+/// The highest version of image ever confirmed, kept by the bootloader in a dedicated region of
+/// flash.
+///
+/// This provides anti-rollback protection without any secure storage: images older than the
+/// floor can be refused (see [`select_with_version_floor()`][select]). The floor is recorded as
+/// a thermometer code across [`WORDS`][words] words, one cleared bit per version, so raising it
+/// only ever clears bits and it can never be lowered short of erasing the region.
+///
+/// [select]: fn.select_with_version_floor.html
+/// [words]: #associatedconstant.WORDS
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionFloor([u32; VersionFloor::WORDS]);
+
+impl VersionFloor {
+    /// The number of words of flash holding the floor, enough for a bit per version.
+    pub const WORDS: usize = 8;
+
+    /// Interprets the given words of flash as a version floor.
+    pub fn from_words(words: [u32; VersionFloor::WORDS]) -> VersionFloor {
+        VersionFloor(words)
+    }
+    /// Returns the raw value of the floor's words.
+    pub fn words(&self) -> [u32; VersionFloor::WORDS] {
+        self.0
+    }
+    /// Returns the oldest version which may be booted, which is zero for erased flash.
+    pub fn version(&self) -> u8 {
+        let cleared: u32 = self.0.iter().map(|word| word.count_zeros()).sum();
+        cmp::min(cleared, u32::from(u8::MAX)) as u8
+    }
+    /// Raises the floor to the given version and writes the words which changed, starting at
+    /// the given address, through the given writer.
+    ///
+    /// A version at or below the current floor leaves it untouched and nothing is written.
+    pub fn raise<W: FlashWriter>(&mut self, version: u8, addr: u32, writer: &mut W) {
+        let prior = self.0;
+        for (i, word) in self.0.iter_mut().enumerate() {
+            // The number of the word's bits, from its least significant, below the version.
+            let below = cmp::min(u32::from(version).saturating_sub(32 * i as u32), 32);
+            *word &= (!0u64 << below) as u32;
+        }
+        flash::write_changes(writer, addr, bytes(&prior), bytes(&self.0));
+    }
+}
+
+/// Returns the version floor recorded at the given address.
+///
+/// See [`VersionFloor`][floor] for details.
+///
+/// # Safety
+///
+/// The address must point to the words of flash which hold the floor.
+///
+/// [floor]: struct.VersionFloor.html
+pub unsafe fn version_floor(addr: u32) -> u8 {
+    VersionFloor(ptr::read_volatile(
+        addr as *const [u32; VersionFloor::WORDS],
+    ))
+    .version()
+}
+
+/// Raises the version floor at the given address to the given version, writing it through the
+/// given writer.
+///
+/// This is meant to be called once an image has been confirmed, with the image's version.
+///
+/// # Safety
+///
+/// The address must point to the words of flash which hold the floor.
+pub unsafe fn update_version_floor<W: FlashWriter>(addr: u32, version: u8, writer: &mut W) {
+    let mut floor = VersionFloor(ptr::read_volatile(
+        addr as *const [u32; VersionFloor::WORDS],
+    ));
+    floor.raise(version, addr, writer)
+}
+
+/// Handles the violation of an internal invariant, as described by `reason`.
+///
+/// This calls the integrator's `switcher_on_fatal()` if the `fatal-hook` feature is enabled and
+/// panics otherwise.
+#[cfg(feature = "fatal-hook")]
+fn fatal(_reason: &'static str) -> ! {
+    extern "Rust" {
+        fn switcher_on_fatal() -> !;
+    }
+    unsafe { switcher_on_fatal() }
+}
+
+#[cfg(not(feature = "fatal-hook"))]
+fn fatal(reason: &'static str) -> ! {
+    panic!("{}", reason)
+}
+
+impl<'a> TryFrom<&'a mut [u8]> for Image<'a> {
+    type Error = SliceError;
+
+    /// Creates an image from the given buffer, whose last eight bytes hold the footer.
+    ///
+    /// See [`Image::from_slice()`][from_slice] for the layout of the buffer.
+    ///
+    /// [from_slice]: struct.Image.html#method.from_slice
+    fn try_from(buf: &'a mut [u8]) -> Result<Image<'a>, SliceError> {
+        let size = mem::size_of::<Footer>();
+        if buf.len() < size {
+            return Err(SliceError::TooShort);
+        }
+
+        let (contents, footer) = buf.split_at_mut(buf.len() - size);
+        if footer.as_ptr().align_offset(mem::align_of::<Footer>()) != 0 {
+            return Err(SliceError::Misaligned);
+        }
+
+        let footer = unsafe { &mut *(footer.as_mut_ptr() as *mut Footer) };
+        // The length of an erased footer is meaningless, and is never used to locate the image.
+        if !footer.is_erased() && footer.length() as usize > contents.len() {
+            return Err(SliceError::TooLong);
+        }
+
+        Ok(Image {
+            footer,
+            extension: None,
+            build_info: None,
+            cached_crc: None,
+            index: 0,
+        })
+    }
+}
+
+/// The reason a buffer couldn't be interpreted as an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliceError {
+    /// The buffer is too short to hold a footer.
+    TooShort,
+    /// The footer, at the end of the buffer, isn't suitably aligned.
+    Misaligned,
+    /// The length recorded in the footer exceeds the rest of the buffer.
+    TooLong,
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            SliceError::TooShort => "buffer is too short to hold a footer",
+            SliceError::Misaligned => "footer is misaligned",
+            SliceError::TooLong => "image extends beyond the start of the buffer",
+        })
+    }
+}
+
+/// An iterator over the intact images found by scanning a region of flash for their footers.
+///
+/// Candidate footers are considered every `step` bytes from the start of the region, skipping
+/// any that aren't suitably aligned. A candidate is yielded if it isn't erased and its length
+/// and checksum match the bytes immediately preceding it, but its status is not otherwise
+/// considered. Since the yielded images borrow the region mutably, the region is split after
+/// each image's footer; the scan resumes after it, and later images must lie entirely after
+/// it. The yielded images have no extension.
+///
+/// This is intended for recovery and diagnostics, when the location of the images isn't known.
+/// Every candidate requires a pass of the CRC over the preceding bytes, so scanning is slow.
+pub struct FooterScanner<'a> {
+    region: &'a mut [u8],
+    offset: usize,
+    step: usize,
+}
+
+impl<'a> FooterScanner<'a> {
+    /// Creates a scanner over the flash from `start` up to (but excluding) `end`.
+    ///
+    /// # Safety
+    ///
+    /// No verification is performed on the addresses: the entire region must be readable and
+    /// must not be accessed by other means for the lifetime of the scanner. A `start` of zero is
+    /// fatal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is zero.
+    pub unsafe fn new(start: u32, end: u32, step: u32) -> FooterScanner<'a> {
+        if start == 0 {
+            fatal("scan region address is null");
+        }
+        let length = end.saturating_sub(start) as usize;
+        FooterScanner::from_slice(slice::from_raw_parts_mut(start as *mut u8, length), step)
+    }
+
+    /// Creates a scanner over the given buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is zero.
+    pub fn from_slice(region: &'a mut [u8], step: u32) -> FooterScanner<'a> {
+        assert!(step != 0, "step must be non-zero");
+        FooterScanner {
+            region,
+            offset: 0,
+            step: step as usize,
+        }
+    }
+
+    /// Returns true if the candidate footer at the given offset describes an intact image.
+    fn is_intact(&self, offset: usize) -> bool {
+        let footer_size = mem::size_of::<Footer>();
+        let candidate = &self.region[offset..offset + footer_size];
+        if candidate.as_ptr().align_offset(mem::align_of::<Footer>()) != 0 {
+            return false;
+        }
+
+        let mut bits = [0; 8];
+        bits.copy_from_slice(candidate);
+        let footer = Footer(u64::from_ne_bytes(bits));
+        let length = footer.length() as usize;
+        !footer.is_erased()
+            && length != 0
+            && length <= offset
+            && crc::calculate(&self.region[offset - length..offset]) == footer.checksum()
+    }
+}
+
+impl<'a> Iterator for FooterScanner<'a> {
+    type Item = Image<'a>;
+
+    fn next(&mut self) -> Option<Image<'a>> {
+        let footer_size = mem::size_of::<Footer>();
+        loop {
+            let offset = self.offset;
+            let end = offset.checked_add(footer_size)?;
+            if end > self.region.len() {
+                return None;
+            }
+            self.offset = offset.saturating_add(self.step);
+
+            if self.is_intact(offset) {
+                // Resume at the first candidate past the footer, on the same grid of steps.
+                while self.offset < end {
+                    self.offset = self.offset.saturating_add(self.step);
+                }
+                self.offset -= end;
+                let (image, rest) = mem::take(&mut self.region).split_at_mut(end);
+                self.region = rest;
+                return Some(Image::from_slice(image));
+            }
+        }
+    }
+}
+
+/// Images are ordered by version alone (see also
+/// [`Image::is_exact_duplicate_of()`][is_exact_duplicate_of]).
+///
+/// [is_exact_duplicate_of]: struct.Image.html#method.is_exact_duplicate_of
+impl<'a, const MAX_ATTEMPTS: u8> Ord for Image<'a, MAX_ATTEMPTS> {
+    fn cmp(&self, other: &Image<MAX_ATTEMPTS>) -> Ordering {
+        self.footer.version().cmp(&other.footer.version())
+    }
+}
+
+impl<'a, const MAX_ATTEMPTS: u8> PartialOrd for Image<'a, MAX_ATTEMPTS> {
+    fn partial_cmp(&self, other: &Image<MAX_ATTEMPTS>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, const MAX_ATTEMPTS: u8> Eq for Image<'a, MAX_ATTEMPTS> {}
+
+/// Images of the same version are equal, whatever their contents.
+impl<'a, const MAX_ATTEMPTS: u8> PartialEq for Image<'a, MAX_ATTEMPTS> {
+    fn eq(&self, other: &Image<MAX_ATTEMPTS>) -> bool {
+        self.footer.version() == other.footer.version()
+    }
+}
+
+/// Returns the newer, bootable image of the given images.
+///
+/// This first determines which of the images are bootable, updating the image validity if
+/// necessary (see [`Image::verify_bootable()`][verify_bootable] for details). If both image are
+/// bootable, the newer of the two is returned, unless exactly one of them has requested a swap
+/// (see [`Image::request_swap()`][request_swap]), in which case the other is returned. Otherwise,
+/// the only bootable image is returned, if any. Any swap requests are consumed by the selection,
+/// whether or not they could be honored.
+///
+/// A recovery image (see [`Image::is_recovery()`][is_recovery]) is only returned if the other
+/// image isn't bootable, regardless of their versions or any swap request.
+///
+/// Of two bootable images of the same version, the one with more remaining boot attempts is
+/// preferred, so as not to exhaust a slot needlessly. Should they also have the same number of
+/// attempts, they are ordered by the address of their footers (see
+/// [`Image::footer_address()`][footer_address]), with the lower address being preferred. The
+/// result therefore doesn't depend on the order of the arguments.
+///
+/// [verify_bootable]: struct.Image.html#method.verify_bootable
+/// [request_swap]: struct.Image.html#method.request_swap
+/// [footer_address]: struct.Image.html#method.footer_address
+/// [is_recovery]: struct.Image.html#method.is_recovery
+pub fn select<'a, 'b, const MAX_ATTEMPTS: u8>(
+    image_a: &'a mut Image<'b, MAX_ATTEMPTS>,
+    image_b: &'a mut Image<'b, MAX_ATTEMPTS>,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    let swap_a = image_a.take_swap_request();
+    let swap_b = image_b.take_swap_request();
+
+    match (image_a.verify_bootable(), image_b.verify_bootable()) {
+        (true, true) => {
+            if image_a.is_recovery() != image_b.is_recovery() {
+                if image_a.is_recovery() {
+                    Some(image_b)
+                } else {
+                    Some(image_a)
+                }
+            } else if swap_a != swap_b {
+                if swap_a {
+                    Some(image_b)
+                } else {
+                    Some(image_a)
+                }
+            } else {
+                let by_attempts = image_a.attempts().cmp(&image_b.attempts());
+                let by_address = image_b.footer_address().cmp(&image_a.footer_address());
+                let ordering = Ord::cmp(image_a, image_b)
+                    .then(by_attempts)
+                    .then(by_address);
+                if ordering == Ordering::Greater {
+                    Some(image_a)
+                } else {
+                    Some(image_b)
+                }
+            }
+        }
+        (true, false) => Some(image_a),
+        (false, true) => Some(image_b),
+        (false, false) => None,
+    }
+}
+
+/// How long a failed image stays out of consideration (see
+/// [`select_with_failure_policy()`][select_with_failure_policy]).
+///
+/// [select_with_failure_policy]: fn.select_with_failure_policy.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// A failed image is never booted again; this is the behavior of [`select()`][select].
+    ///
+    /// [select]: fn.select.html
+    StickyFailure,
+    /// A failed image is reconsidered once a newer image in the other slot has been confirmed.
+    FailureClearsOnSiblingSuccess,
+}
+
+/// Returns the newer, bootable image of the given images, applying the given failure policy.
+///
+/// With [`FailurePolicy::StickyFailure`][sticky], this is identical to [`select()`][select].
+/// With [`FailurePolicy::FailureClearsOnSiblingSuccess`][soft], the failure of either image is
+/// first forgiven (see [`Extension::forgiven()`][forgiven]) if its sibling is newer and has been
+/// marked as having successfully booted, since that sibling was written after the failure was
+/// recorded. The image is then selected as if it had never failed: its remaining attempts are
+/// left as they were, and the confirmed sibling, being newer, is still preferred. An image whose
+/// footer is unsound (see [`Footer::sane()`][sane]) isn't forgiven, nor is one without an
+/// extension in which to record the forgiveness.
+///
+/// Forgiving a failure only clears a bit of the extension, so it can be persisted without
+/// erasing the page.
+///
+/// [sticky]: enum.FailurePolicy.html#variant.StickyFailure
+/// [soft]: enum.FailurePolicy.html#variant.FailureClearsOnSiblingSuccess
+/// [forgiven]: struct.Extension.html#method.forgiven
+/// [sane]: struct.Footer.html#method.sane
+/// [select]: fn.select.html
+pub fn select_with_failure_policy<'a, 'b, const MAX_ATTEMPTS: u8>(
+    image_a: &'a mut Image<'b, MAX_ATTEMPTS>,
+    image_b: &'a mut Image<'b, MAX_ATTEMPTS>,
+    policy: FailurePolicy,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    if policy == FailurePolicy::FailureClearsOnSiblingSuccess {
+        forgive_failure(image_a, image_b);
+        forgive_failure(image_b, image_a);
+    }
+    select(image_a, image_b)
+}
+
+/// Forgives the failure of `image` if its footer is sound and `sibling` is a newer, confirmed
+/// image.
+fn forgive_failure<const MAX_ATTEMPTS: u8>(
+    image: &mut Image<MAX_ATTEMPTS>,
+    sibling: &Image<MAX_ATTEMPTS>,
+) {
+    let confirmed = sibling.is_confirmed() && !sibling.is_failed();
+    let failed = image.is_failed() && !image.is_confirmed() && image.sane();
+    if failed && confirmed && *sibling > *image {
+        if let Some(ref mut extension) = image.extension {
+            extension.set_forgiven()
+        }
+    }
+}
+
+/// Returns the bootability of each of the given images.
+///
+/// Unlike [`select()`][select], this uses [`Image::is_bootable()`][is_bootable], so none of the
+/// images' footers are modified. This is intended for diagnostics.
+///
+/// [select]: fn.select.html
+/// [is_bootable]: struct.Image.html#method.is_bootable
+pub fn survey<const N: usize, const MAX_ATTEMPTS: u8>(
+    images: &[Image<MAX_ATTEMPTS>; N],
+) -> [bool; N] {
+    let mut bootable = [false; N];
+    for (bootable, image) in bootable.iter_mut().zip(images) {
+        *bootable = image.is_bootable();
+    }
+    bootable
+}
+
+/// Returns the newest bootable image of the given images, along with the status of each image.
+///
+/// The selection is the same as that of [`select_by()`][select_by] using [`Image::cmp()`][cmp].
+/// The statuses are those found while verifying the images, in the same order as the images, so
+/// that the caller can act upon the rejected ones (e.g. by erasing a slot whose checksum is
+/// invalid). The selected image's status is included as well.
+///
+/// [select_by]: fn.select_by.html
+/// [cmp]: struct.Image.html#method.cmp
+pub fn select_verbose<'a, 'b, const N: usize, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>; N],
+) -> (Option<&'a mut Image<'b, MAX_ATTEMPTS>>, [BootStatus; N]) {
+    let best = select_max_index(&mut images[..], |_| true, Image::cmp);
+    let mut statuses = [BootStatus::Erased; N];
+    for (status, image) in statuses.iter_mut().zip(images.iter()) {
+        *status = image.verified_status();
+    }
+    (best.map(move |i| &mut images[i]), statuses)
+}
+
+/// Returns the indices of the bootable images of the given images, newest first.
+///
+/// The images are verified exactly as by [`select_by()`][select_by] and ranked by their natural
+/// ordering, with recovery images last, so the first index is that of the image which
+/// `select_by()` would return using [`Image::cmp()`][cmp] and the rest give the order in which
+/// the others would be fallen back upon. Unbootable images are omitted; the array is padded with
+/// `None` after the last bootable image.
+///
+/// [select_by]: fn.select_by.html
+/// [cmp]: struct.Image.html#method.cmp
+pub fn rank_bootable<const N: usize, const MAX_ATTEMPTS: u8>(
+    images: &mut [Image<MAX_ATTEMPTS>; N],
+) -> [Option<usize>; N] {
+    let mut ranked = [None; N];
+    let mut count = 0;
+    for i in 0..considered(N) {
+        if !images[i].verify_bootable() {
+            continue;
+        }
+
+        // Of several equal images, select_by() returns the last, so it is ranked first.
+        let rank = |&j: &Option<usize>| match j {
+            Some(j) => {
+                by_recovery(&images[i], &images[j]).then_with(|| images[i].cmp(&images[j]))
+                    == Ordering::Less
+            }
+            None => false,
+        };
+        let position = ranked[..count].iter().take_while(|j| rank(j)).count();
+        ranked[position..=count].rotate_right(1);
+        ranked[position] = Some(i);
+        count += 1;
+    }
+    ranked
+}
+
+/// Explains why none of the given images can be booted, or returns `None` if one can.
+///
+/// The images are verified exactly as by [`select_by()`][select_by], so this may be called
+/// before or after a selection which came up empty, with the same outcome. Empty slots (and
+/// images which are being written) are disregarded when the reason is determined, so that, for
+/// example, a single failed image alongside an empty slot is reported as
+/// [`SelectError::AllFailed`][all_failed].
+///
+/// [select_by]: fn.select_by.html
+/// [all_failed]: enum.SelectError.html#variant.AllFailed
+pub fn select_reason<const MAX_ATTEMPTS: u8>(
+    images: &mut [Image<MAX_ATTEMPTS>],
+) -> Option<SelectError> {
+    let mut reason = SelectError::NoImages;
+    let considered = considered(images.len());
+    for image in &mut images[..considered] {
+        if image.verify_bootable() {
+            return None;
+        }
+
+        let this = match image.verified_status() {
+            BootStatus::Erased => continue,
+            BootStatus::Failed => SelectError::AllFailed,
+            BootStatus::Exhausted => SelectError::AllExhausted,
+            BootStatus::Unsound
+            | BootStatus::Corrupt
+            | BootStatus::Invalid
+            | BootStatus::ChecksumInvalid => SelectError::AllInvalid,
+            BootStatus::Trial | BootStatus::Confirmed => return None,
+        };
+        reason = match reason {
+            SelectError::NoImages => this,
+            reason if reason == this => reason,
+            _ => SelectError::Mixed,
+        };
+    }
+    Some(reason)
+}
+
+/// The reason none of a set of images can be booted (see [`select_reason()`][select_reason]).
+///
+/// [select_reason]: fn.select_reason.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectError {
+    /// There are no images; every slot is empty.
+    NoImages,
+    /// Every image has been marked as having failed to boot.
+    AllFailed,
+    /// Every image has run out of boot attempts.
+    AllExhausted,
+    /// Every image is invalid, whether marked so or because its checksum or footer is bad.
+    AllInvalid,
+    /// The images are unbootable for different reasons.
+    Mixed,
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            SelectError::NoImages => "no images",
+            SelectError::AllFailed => "all images have failed",
+            SelectError::AllExhausted => "all images have run out of attempts",
+            SelectError::AllInvalid => "all images are invalid",
+            SelectError::Mixed => "no image is bootable",
+        })
+    }
+}
+
+/// Determines the boot status of an image from the raw value of its footer and its contents.
+///
+/// This makes the same decisions as [`Image::verify_bootable()`][verify_bootable], in the same
+/// order, but is a pure function: nothing is written and no memory beyond `image` is read. The
+/// image's contents are taken to be the final [`length()`][length] bytes of `image` (so `image`
+/// may be, for example, everything in flash preceding the footer); a length exceeding `image`
+/// makes the footer unsound. Since only the footer is given, the status held by an
+/// [`Extension`][extension] is not taken into account, and the image is given the default
+/// budget of attempts.
+///
+/// [verify_bootable]: struct.Image.html#method.verify_bootable
+/// [length]: struct.Footer.html#method.length
+/// [extension]: struct.Extension.html
+pub fn evaluate_footer(footer_bits: u64, image: &[u8]) -> BootStatus {
+    let footer = Footer(footer_bits);
+    if footer.is_erased() {
+        return BootStatus::Erased;
+    }
+
+    let length = footer.length() as usize;
+    if length == 0 || length > image.len() || (footer.success() && footer.failure()) {
+        return BootStatus::Unsound;
+    }
+
+    if footer.is_corrupt() {
+        return BootStatus::Corrupt;
+    }
+
+    if footer.success() {
+        return BootStatus::Confirmed;
+    }
+
+    if footer.failure() {
+        return BootStatus::Failed;
+    }
+
+    if footer.invalid() {
+        return BootStatus::Invalid;
+    }
+
+    if !footer.valid() && crc::calculate(&image[image.len() - length..]) != footer.checksum() {
+        return BootStatus::ChecksumInvalid;
+    }
+
+    if footer.attempts() > 0 {
+        BootStatus::Trial
+    } else {
+        BootStatus::Exhausted
+    }
+}
+
+/// Returns the best bootable image of the given images, according to the given comparator.
+///
+/// This first determines which of the images are bootable, updating the image validity if
+/// necessary (see [`Image::verify_bootable()`][verify_bootable] for details). Of the bootable
+/// images, the greatest according to `cmp` is returned, if any. If several images compare equal,
+/// the last of them is returned. Recovery images (see [`Image::is_recovery()`][is_recovery]) are
+/// only considered if none of the others are bootable; this holds for all the selections built
+/// upon this function.
+///
+/// [`select()`][select] is equivalent to this function with a comparison by version.
+///
+/// [verify_bootable]: struct.Image.html#method.verify_bootable
+/// [is_recovery]: struct.Image.html#method.is_recovery
+/// [select]: fn.select.html
+pub fn select_by<'a, 'b, F, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    cmp: F,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>>
+where
+    F: Fn(&Image<'b, MAX_ATTEMPTS>, &Image<'b, MAX_ATTEMPTS>) -> Ordering,
+{
+    select_max(images, |_| true, cmp)
+}
+
+/// Boots the newest bootable image of the given images or, if there is none, halts.
+///
+/// The image is selected as by [`select_by()`][select_by] using the images' natural ordering and
+/// started with [`Image::boot()`][boot]. Without a bootable image, the processor waits for
+/// interrupts forever (on targets other than ARM, it spins instead).
+///
+/// With the `sim` feature enabled, halting is recorded (see [`sim::halted()`][halted]) and the
+/// calling thread panics, just as it does for a simulated boot.
+///
+/// [select_by]: fn.select_by.html
+/// [boot]: struct.Image.html#method.boot
+/// [halted]: sim/fn.halted.html
+pub fn select_and_boot<const MAX_ATTEMPTS: u8>(images: &mut [Image<MAX_ATTEMPTS>]) -> ! {
+    match select_by(images, Image::cmp) {
+        Some(image) => image.boot(),
+        None => halt(),
+    }
+}
+
+/// Returns the newest bootable image of the given images or, if there is none, the given
+/// recovery action.
+///
+/// The image is selected as by [`select_by()`][select_by] using the images' natural ordering, so
+/// a recovery image (see [`Image::is_recovery()`][is_recovery]) is returned ahead of the action.
+/// The action is only returned once nothing at all can be booted, which makes that path as easy
+/// to test as any other selection; carrying it out is left to the caller.
+///
+/// [select_by]: fn.select_by.html
+/// [is_recovery]: struct.Image.html#method.is_recovery
+pub fn select_or_recover<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    policy: RecoveryAction,
+) -> SelectOutcome<'a, 'b, MAX_ATTEMPTS> {
+    match select_by(images, Image::cmp) {
+        Some(image) => SelectOutcome::Boot(image),
+        None => SelectOutcome::Recover(policy),
+    }
+}
+
+/// What to do when no image can be booted (see [`select_or_recover()`][select_or_recover]).
+///
+/// [select_or_recover]: fn.select_or_recover.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// Enter the device firmware update mode at the given address (e.g. a ROM bootloader).
+    EnterDfu(u32),
+    /// Halt the processor, waiting for a debugger or a power cycle.
+    Halt,
+    /// Reset the processor, in the hope that a later attempt succeeds.
+    Reset,
+}
+
+/// The result of [`select_or_recover()`][select_or_recover].
+///
+/// [select_or_recover]: fn.select_or_recover.html
+#[derive(Debug)]
+pub enum SelectOutcome<'a, 'b, const MAX_ATTEMPTS: u8 = 4> {
+    /// The image to boot.
+    Boot(&'a mut Image<'b, MAX_ATTEMPTS>),
+    /// No image can be booted; the action should be carried out instead.
+    Recover(RecoveryAction),
+}
+
+/// Returns the newest bootable image of the given images, tolerating a wrap of the version.
+///
+/// This is identical to [`select_by()`][select_by] using
+/// [`Image::cmp_wrapping()`][cmp_wrapping], so that an image whose version has just wrapped
+/// around to zero is still preferred over one at version 255.
+///
+/// [select_by]: fn.select_by.html
+/// [cmp_wrapping]: struct.Image.html#method.cmp_wrapping
+pub fn select_wrapping<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    select_by(images, Image::cmp_wrapping)
+}
+
+/// Returns the oldest bootable image of the given images.
+///
+/// This is intended for testing downgrades and is not a boot policy; production bootloaders
+/// should use [`select()`][select] or one of its variants. The images are verified exactly as by
+/// [`select_by()`][select_by], but the bootable image with the lowest version is returned.
+///
+/// [select]: fn.select.html
+/// [select_by]: fn.select_by.html
+pub fn select_oldest<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    select_by(images, |a, b| b.cmp(a))
+}
+
+/// Returns the bootable image of the given version, or else the newest bootable image.
+///
+/// This allows a specific version to be forced (e.g. to reproduce a bug) without bypassing any of
+/// the checks performed by [`Image::verify_bootable()`][verify_bootable]: if no image of the
+/// given version is bootable, the selection is the same as that of [`select_by()`][select_by]
+/// using the images' natural ordering.
+///
+/// [verify_bootable]: struct.Image.html#method.verify_bootable
+/// [select_by]: fn.select_by.html
+pub fn select_preferring_version<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    version: u8,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    select_by(images, |a, b| {
+        let preferred = |image: &Image<MAX_ATTEMPTS>| image.footer.version() == version;
+        preferred(a).cmp(&preferred(b)).then_with(|| a.cmp(b))
+    })
+}
+
+/// Returns the newest bootable image, preferring one in the active bank among the newest.
+///
+/// This is for parts with independent flash banks, only one of which is mapped at the boot
+/// address at a time. `bank_of` identifies the bank in which each image resides. A strictly newer
+/// image is selected wherever it resides, but an image in `active_bank` wins a version tie so
+/// that the banks needn't be swapped. Otherwise, the selection is the same as that of
+/// [`select_by()`][select_by] using the images' natural ordering.
+///
+/// [select_by]: fn.select_by.html
+pub fn select_with_bank<'a, 'b, F, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    active_bank: u8,
+    bank_of: F,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>>
+where
+    F: Fn(&Image<'b, MAX_ATTEMPTS>) -> u8,
+{
+    select_by(images, |a, b| {
+        let active = |image| bank_of(image) == active_bank;
+        a.cmp(b).then_with(|| active(a).cmp(&active(b)))
+    })
+}
+
+/// Returns the newest image of the given images which is both bootable and allowed.
+///
+/// This first determines which of the images are bootable, updating the image validity if
+/// necessary (see [`Image::verify_bootable()`][verify_bootable] for details). The predicate is
+/// then evaluated for each of the bootable images and the newest of those which are allowed is
+/// returned, if any. A predicate which rejects every image (e.g. while the board is in a
+/// manufacturing test mode) prevents anything from being booted.
+///
+/// [verify_bootable]: struct.Image.html#method.verify_bootable
+pub fn select_if<'a, 'b, F, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    allow: F,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>>
+where
+    F: Fn(&Image<'b, MAX_ATTEMPTS>) -> bool,
+{
+    select_max(images, allow, Image::cmp)
+}
+
+/// Returns the newest bootable image or, failing that, the newest image with a valid checksum.
+///
+/// When an image is bootable, the selection is the same as that of [`select_by()`][select_by]
+/// using the images' natural ordering. Otherwise, as a last resort, images which have run out of
+/// attempts or have been marked as failed are considered too, provided that their checksum is
+/// valid. Booting such an image is a risk which the caller should record.
+///
+/// [select_by]: fn.select_by.html
+pub fn select_with_fallback<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    let best = select_max_index(images, |_| true, Image::cmp).or_else(|| {
+        let mut best: Option<usize> = None;
+        for i in 0..considered(images.len()) {
+            if !images[i].checksum_intact() {
+                continue;
+            }
+
+            best = match best {
+                Some(b) if images[i] < images[b] => Some(b),
+                _ => Some(i),
+            };
+        }
+        best
+    });
+    best.map(move |i| &mut images[i])
+}
+
+/// Returns the newest bootable image of the given images which is compatible with the board.
+///
+/// This is identical to [`select_if()`][select_if] using
+/// [`Image::supports_hardware()`][supports_hardware]; an image built for another board revision
+/// is never selected, however new it is.
+///
+/// [select_if]: fn.select_if.html
+/// [supports_hardware]: struct.Image.html#method.supports_hardware
+pub fn select_for_hardware<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    hw_rev: u8,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    select_if(images, |image| image.supports_hardware(hw_rev))
+}
+
+/// Returns the newest bootable image of the given images which the running bootloader supports.
+///
+/// This is identical to [`select_if()`][select_if] using
+/// [`Image::supports_bootloader()`][supports_bootloader], so that an image which relies on a
+/// newer bootloader is never started by an older one.
+///
+/// [select_if]: fn.select_if.html
+/// [supports_bootloader]: struct.Image.html#method.supports_bootloader
+pub fn select_compatible<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    bootloader_version: u8,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    select_if(images, |image| {
+        image.supports_bootloader(bootloader_version)
+    })
+}
+
+/// Returns the newest bootable image of the given images which isn't older than the given version
+/// floor.
+///
+/// This is identical to [`select_if()`][select_if], rejecting images whose version is below
+/// `floor`, which is typically read with [`version_floor()`][version_floor].
+///
+/// [select_if]: fn.select_if.html
+/// [version_floor]: fn.version_floor.html
+pub fn select_with_version_floor<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    floor: u8,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    select_if(images, |image| image.footer.version() >= floor)
+}
+
+/// Returns the newest bootable image of the given images which the shared boot state permits,
+/// updating the state to match.
+///
+/// Images older than the state's rollback counter (see
+/// [`BootState::rollback_counter()`][rollback_counter]) are never selected; otherwise, the
+/// selection is the same as that of [`select_by()`][select_by] using the images' natural
+/// ordering. If an image is selected, its position in `images` is recorded as the active slot
+/// and the boot is counted (see [`BootState::boot_loop_count()`][boot_loop_count]); the count
+/// starts afresh whenever the active slot changes. If the selected image has been confirmed,
+/// the rollback counter is raised to its version, so that older images can no longer be
+/// booted, and the boot loop count is reset. The state is only modified in memory; it is up to
+/// the caller to write it back to flash.
+///
+/// [rollback_counter]: struct.BootState.html#method.rollback_counter
+/// [select_by]: fn.select_by.html
+/// [boot_loop_count]: struct.BootState.html#method.boot_loop_count
+pub fn select_with_state<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    state: &mut BootState,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    select_with_state_limited(images, state, None)
+}
+
+/// Returns the image to boot according to the shared boot state, falling back on another image
+/// once the active slot appears to be stuck in a boot loop.
+///
+/// This is identical to [`select_with_state()`][select_with_state], except that once the active
+/// slot has been booted `max_unconfirmed` times in a row without being confirmed, the best of
+/// the other bootable images (which may be a recovery image) is selected instead, without
+/// waiting for the looping image to run out of attempts. The looping image is recorded as stuck
+/// (see [`BootState::stuck_slot()`][stuck_slot]) and passed over by every later selection until
+/// it is replaced by another version or confirmed. Should there be no other bootable image, the
+/// looping image is selected as usual.
+///
+/// [select_with_state]: fn.select_with_state.html
+/// [stuck_slot]: struct.BootState.html#method.stuck_slot
+pub fn select_with_loop_detection<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    state: &mut BootState,
+    max_unconfirmed: u8,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    select_with_state_limited(images, state, Some(max_unconfirmed))
+}
+
+fn select_with_state_limited<'a, 'b, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    state: &mut BootState,
+    max_unconfirmed: Option<u8>,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>> {
+    let floor = state.rollback_counter();
+    let allow = |image: &Image<'b, MAX_ATTEMPTS>| image.footer.version() >= floor;
+    let mut best = select_max_index(images, allow, Image::cmp)?;
+
+    // A stuck image is forgiven once it has been replaced or confirmed.
+    if let Some(slot) = state.stuck_slot() {
+        let forgiven = match images.get(usize::from(slot)) {
+            Some(image) => {
+                image.footer.version() != state.stuck_version() || image.footer.success()
+            }
+            None => true,
+        };
+        if forgiven {
+            state.clear_stuck_slot();
+        }
+    }
+
+    if let Some(max) = max_unconfirmed {
+        let looping = state.boot_loop_count() >= max;
+        if looping && state.active_slot() == Some(best as u8) && !images[best].footer.success() {
+            state.set_stuck_slot(best as u8, images[best].footer.version());
+        }
+
+        if state.stuck_slot() == Some(best as u8) {
+            let stuck: *const Image<'b, MAX_ATTEMPTS> = &images[best];
+            let others = |image: &Image<'b, MAX_ATTEMPTS>| allow(image) && !ptr::eq(image, stuck);
+            if let Some(fallback) = select_max_index(images, others, Image::cmp) {
+                best = fallback;
+            }
+        }
+    }
+
+    let image = &mut images[best];
+    if state.active_slot() != Some(best as u8) {
+        state.set_active_slot(best as u8);
+        state.reset_boot_loop_count();
+    }
+    if image.footer.success() {
+        state.raise_rollback_counter(image.footer.version());
+        state.reset_boot_loop_count();
+    } else {
+        state.increment_boot_loop_count();
+    }
+    Some(image)
+}
+
+/// What becomes of the previous image once an update has been confirmed (see
+/// [`confirm_update()`][confirm_update]).
+///
+/// [confirm_update]: fn.confirm_update.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fallback {
+    /// The previous image is left untouched.
+    Keep,
+    /// The previous image is left with at most the given number of boot attempts.
+    ///
+    /// Attempts are only ever consumed, never restored. Note that the attempts of an image which
+    /// has been marked as having successfully booted are not consulted.
+    Attempts(usize),
+    /// The previous image is marked as having failed, so it is never booted again.
+    Retire,
+}
+
+/// Confirms an updated image and applies the given policy to the image it replaced.
+///
+/// The new image is marked as having successfully booted (see
+/// [`Image::mark_success()`][mark_success]) and the old image is then adjusted as described by
+/// `fallback`. Neither image is persisted.
+///
+/// [mark_success]: struct.Image.html#method.mark_success
+pub fn confirm_update<const MAX_ATTEMPTS: u8>(
+    new: &mut Image<MAX_ATTEMPTS>,
+    old: &mut Image<MAX_ATTEMPTS>,
+    fallback: Fallback,
+) {
+    new.mark_success();
+    match fallback {
+        Fallback::Keep => {}
+        Fallback::Attempts(attempts) => {
+            // A confirmed image reports its full budget however many are consumed, so this
+            // counts the consumptions rather than waiting for the budget to drop.
+            for _ in attempts..old.attempts() {
+                old.decrement_attempts();
+            }
+        }
+        Fallback::Retire => old.mark_failure(),
+    }
+}
+
+/// Returns the greatest image, according to `cmp`, which is both bootable and allowed.
+///
+/// If several images compare equal, the last of them is returned.
+fn select_max<'a, 'b, P, F, const MAX_ATTEMPTS: u8>(
+    images: &'a mut [Image<'b, MAX_ATTEMPTS>],
+    allow: P,
+    cmp: F,
+) -> Option<&'a mut Image<'b, MAX_ATTEMPTS>>
+where
+    P: Fn(&Image<'b, MAX_ATTEMPTS>) -> bool,
+    F: Fn(&Image<'b, MAX_ATTEMPTS>, &Image<'b, MAX_ATTEMPTS>) -> Ordering,
+{
+    select_max_index(images, allow, cmp).map(move |i| &mut images[i])
+}
+
+/// Returns how many of the given number of images a selection considers (see
+/// [`MAX_IMAGES`][max]).
+///
+/// [max]: constant.MAX_IMAGES.html
+fn considered(images: usize) -> usize {
+    debug_assert!(images <= MAX_IMAGES, "more than MAX_IMAGES images");
+    cmp::min(images, MAX_IMAGES)
+}
+
+/// Orders a recovery image below any other.
+fn by_recovery<const MAX_ATTEMPTS: u8>(
+    a: &Image<MAX_ATTEMPTS>,
+    b: &Image<MAX_ATTEMPTS>,
+) -> Ordering {
+    b.is_recovery().cmp(&a.is_recovery())
+}
+
+/// Returns the index of the image which [`select_max()`][select_max] would return.
+///
+/// [select_max]: fn.select_max.html
+fn select_max_index<'b, P, F, const MAX_ATTEMPTS: u8>(
+    images: &mut [Image<'b, MAX_ATTEMPTS>],
+    allow: P,
+    cmp: F,
+) -> Option<usize>
+where
+    P: Fn(&Image<'b, MAX_ATTEMPTS>) -> bool,
+    F: Fn(&Image<'b, MAX_ATTEMPTS>, &Image<'b, MAX_ATTEMPTS>) -> Ordering,
+{
+    let mut best: Option<usize> = None;
+    for i in 0..considered(images.len()) {
+        if !images[i].verify_bootable() || !allow(&images[i]) {
+            continue;
+        }
+
+        best = match best {
+            Some(b)
+                if by_recovery(&images[i], &images[b])
+                    .then_with(|| cmp(&images[i], &images[b]))
+                    == Ordering::Less =>
+            {
+                Some(b)
+            }
+            _ => Some(i),
+        };
+    }
+    best
+}
+
+bitfield!{
+    /// The footer for a bootable image.
+    ///
+    /// This struct should be initialized with 1s except for the length, checksum, and version when
+    /// it is flashed. The struct must also follow the image it describes such that the checksum
+    /// immediately follows the image.
+    ///
+    /// The status bits are stored negated, so that every transition made while selecting and
+    /// booting an image (`set_valid()`, `set_invalid()`, `set_success()`, `set_failure()`, and
+    /// `decrement_attempts()`) only clears bits and can be programmed without an erase. Only
+    /// `reset_attempts()`, `reset_status()`, and `copy_status_from()` may set bits.
+    pub struct Footer(u64);
+
+    /// Returns the checksum of the image.
+    pub u32, checksum, _: 23, 0;
+    /// Returns the version of the image.
+    pub u8, version, _: 31, 24;
+    /// Returns the length (in bytes) of the image, or its low 24 bits for images of 16 MiB or
+    /// more (see `Image::length()`).
+    pub u32, length, _: 55, 32;
+    n_valid, set_n_valid: 56;
+    n_invalid, set_n_invalid: 57;
+    n_success, set_n_success: 58;
+    n_failure, set_n_failure: 59;
+    u8, n_attempts, set_n_attempts: 63, 60;
+}
+
+// A sum wider than the checksum field would be silently truncated when recorded.
+const _: () = assert!(Footer::CHECKSUM_FIELD_BITS == crc::CRC_WIDTH_BITS);
+const _: () = assert!(Footer::FOOTER_SIZE == mem::size_of::<Footer>());
+
+impl Footer {
+    /// The maximum number of boot attempts an image can be given.
+    pub const MAX_ATTEMPTS: usize = 4;
+    /// The revision of the status encoding produced by [`encode_status()`][encode_status].
+    ///
+    /// [encode_status]: #method.encode_status
+    pub const STATUS_ENCODING: u8 = 1;
+    /// The width of the footer's checksum field, in bits.
+    ///
+    /// This must match [`crc::CRC_WIDTH_BITS`][width], which is checked when the crate is built.
+    ///
+    /// [width]: crc/constant.CRC_WIDTH_BITS.html
+    pub const CHECKSUM_FIELD_BITS: usize = 24;
+    /// The size of a footer in flash, in bytes.
+    pub const FOOTER_SIZE: usize = 8;
+
+    /// Creates a pristine footer describing an image with the given version, length, and
+    /// checksum.
+    ///
+    /// The status bits are left erased, as they should be when the footer is flashed. Only the
+    /// low 24 bits of the length and checksum are recorded; the upper bits of the length of a
+    /// larger image, and of a 32-bit checksum, belong in its extension (see
+    /// [`Extension::length_hi()`][length_hi] and [`Extension::checksum_hi()`][checksum_hi]).
+    ///
+    /// [length_hi]: struct.Extension.html#method.length_hi
+    /// [checksum_hi]: struct.Extension.html#method.checksum_hi
+    pub fn new(version: u8, length: u32, checksum: u32) -> Footer {
+        Footer(
+            0xFF << 56
+                | u64::from(length & 0xFF_FFFF) << 32
+                | u64::from(version) << 24
+                | u64::from(checksum & 0xFF_FFFF),
+        )
+    }
+    /// Returns the raw 64-bit value of the footer.
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+    /// Returns true if this footer can be reached from `prior` by clearing bits alone.
+    ///
+    /// Such a change can be programmed over `prior` without erasing the flash.
+    pub fn only_clears(&self, prior: &Footer) -> bool {
+        self.0 & !prior.0 == 0
+    }
+    /// Returns true if the image has been marked valid.
+    pub fn valid(&self) -> bool {
+        !self.n_valid()
+    }
+    /// Marks the image as being valid.
+    pub fn set_valid(&mut self) {
+        self.set_n_valid(false)
+    }
+    /// Returns true if the image has been marked invalid.
+    pub fn invalid(&self) -> bool {
+        !self.n_invalid()
+    }
+    /// Marks the image as being invalid.
+    pub fn set_invalid(&mut self) {
+        self.set_n_invalid(false)
+    }
+    /// Returns true if the image has been marked as having successfully booted.
+    pub fn success(&self) -> bool {
+        !self.n_success()
+    }
+    /// Marks the image as having successfully booted.
+    pub fn set_success(&mut self) {
+        self.set_n_success(false)
+    }
+    /// Returns true if the image has been marked as having failed to boot.
+    pub fn failure(&self) -> bool {
+        !self.n_failure()
+    }
+    /// Marks the image as having failed to boot.
+    pub fn set_failure(&mut self) {
+        self.set_n_failure(false)
+    }
+    /// Returns the number of remaining boot attempts.
+    ///
+    /// The attempts are stored as a thermometer code, one bit per attempt, so that consuming an
+    /// attempt only ever clears a bit. This is the count recorded in the footer, even once it
+    /// has been marked as having successfully booted; see `Image::attempts()` for the budget
+    /// which a confirmed image reports.
+    pub fn attempts(&self) -> usize {
+        self.n_attempts().count_ones() as usize
+    }
+    /// Decrements the number of remaining boot attempts by one.
+    ///
+    /// Once the attempts have been exhausted, this has no effect.
+    pub fn decrement_attempts(&mut self) {
+        let attempts = self.n_attempts() << 1;
+        self.set_n_attempts(attempts)
+    }
+    /// Restores the number of remaining boot attempts to [`MAX_ATTEMPTS`][max].
+    ///
+    /// [max]: #associatedconstant.MAX_ATTEMPTS
+    pub fn reset_attempts(&mut self) {
+        self.set_n_attempts(!0)
+    }
+    /// Restores the footer's status to the pristine state in which it was flashed.
+    ///
+    /// The valid, invalid, success, and failure markers are cleared and the remaining boot
+    /// attempts are restored to [`MAX_ATTEMPTS`][max], so that the image will be evaluated afresh.
+    /// The checksum, version, and length are left untouched. Note that this sets bits, so the
+    /// footer must be erased before the result can be written to flash.
+    ///
+    /// [max]: #associatedconstant.MAX_ATTEMPTS
+    pub fn reset_status(&mut self) {
+        self.set_n_valid(true);
+        self.set_n_invalid(true);
+        self.set_n_success(true);
+        self.set_n_failure(true);
+        self.reset_attempts()
+    }
+    /// Copies the status of another footer onto this one.
+    ///
+    /// The valid, invalid, success, and failure markers and the remaining boot attempts are
+    /// copied, allowing the state of a known-good image to be replicated onto a verified
+    /// duplicate of it. The checksum, version, and length describe the image itself and are left
+    /// untouched.
+    pub fn copy_status_from(&mut self, other: &Footer) {
+        self.set_n_valid(other.n_valid());
+        self.set_n_invalid(other.n_invalid());
+        self.set_n_success(other.n_success());
+        self.set_n_failure(other.n_failure());
+        self.set_n_attempts(other.n_attempts());
+    }
+    /// Converts a footer written in the original layout into the current one.
+    ///
+    /// The original layout matches the current one, except that it records a single boot attempt
+    /// in bit 63, leaving bits 60 through 62 unused (and therefore erased). Read as-is, a footer
+    /// whose attempt had been consumed would appear to have three attempts remaining, so the
+    /// attempt is carried over on its own: the result has one attempt if the original did, and
+    /// none otherwise. Every other field, including combinations of markers which a well-behaved
+    /// bootloader never produces, is preserved bit for bit. The result only ever clears bits of
+    /// the original, so it can be written over it in place.
+    pub fn migrate_from_v1(old: u64) -> Footer {
+        let mut footer = Footer(old);
+        let attempts = if old & 1 << 63 == 0 { 0 } else { 0b1000 };
+        footer.set_n_attempts(attempts);
+        footer
+    }
+    /// Calculates the checksum of the given image and records it in the footer.
+    ///
+    /// The checksum covers exactly the image's bytes, which immediately precede the footer in
+    /// flash; no part of the footer is included. The sum is the one returned by
+    /// [`crc::calculate()`][calculate], so the image followed by the sum in big-endian order
+    /// passes [`crc::is_valid()`][is_valid]. Note that the footer itself is stored
+    /// little-endian. Any checksum already recorded is replaced, so unless the footer's checksum
+    /// field is still erased, the flash must be erased before the result can be written.
+    ///
+    /// [calculate]: crc/fn.calculate.html
+    /// [is_valid]: crc/fn.is_valid.html
+    pub fn compute_and_set_checksum(&mut self, image: &[u8]) {
+        self.0 = self.0 & !0xFF_FFFF | u64::from(crc::calculate(image));
+    }
+    /// Returns every field of the footer, decoded.
+    pub fn describe(&self) -> FooterInfo {
+        FooterInfo {
+            checksum: self.checksum(),
+            version: self.version(),
+            length: self.length(),
+            valid: self.valid(),
+            invalid: self.invalid(),
+            success: self.success(),
+            failure: self.failure(),
+            attempts: self.attempts(),
+        }
+    }
+    /// Encodes the footer's status for a diagnostics protocol.
+    ///
+    /// The encoding is a stable wire format, independent of the footer's layout in flash:
+    ///
+    /// | Byte | Contents                                                                      |
+    /// |-----:|:------------------------------------------------------------------------------|
+    /// |    0 | The version of the image.                                                     |
+    /// |    1 | The number of remaining boot attempts.                                        |
+    /// |    2 | The status markers: valid (bit 0), invalid (1), success (2), and failure (3). |
+    /// |    3 | The revision of the encoding, [`STATUS_ENCODING`][encoding].                  |
+    ///
+    /// Set markers are encoded as 1s, and the unused bits of byte 2 are zero. The encoding is
+    /// decoded by [`decode_status()`][decode_status].
+    ///
+    /// [encoding]: #associatedconstant.STATUS_ENCODING
+    /// [decode_status]: #method.decode_status
+    pub fn encode_status(&self) -> [u8; 4] {
+        let flags = StatusFlags {
+            valid: self.valid(),
+            invalid: self.invalid(),
+            success: self.success(),
+            failure: self.failure(),
+        };
+        [
+            self.version(),
+            self.attempts() as u8,
+            flags.bits(),
+            Footer::STATUS_ENCODING,
+        ]
+    }
+    /// Decodes a footer's status, as encoded by [`encode_status()`][encode_status], into its
+    /// version, remaining boot attempts, and status markers.
+    ///
+    /// Returns `None` if the encoding is of another revision. Unused bits are ignored.
+    ///
+    /// [encode_status]: #method.encode_status
+    pub fn decode_status(encoded: [u8; 4]) -> Option<(u8, usize, StatusFlags)> {
+        if encoded[3] != Footer::STATUS_ENCODING {
+            return None;
+        }
+        Some((
+            encoded[0],
+            usize::from(encoded[1]),
+            StatusFlags::from_bits(encoded[2]),
+        ))
+    }
+    /// Returns the CRC-16 of the footer's metadata: its checksum, version, and length.
+    ///
+    /// The image's checksum doesn't cover the rest of the footer, so this can be recorded in the
+    /// image's extension (see [`Image::seal_footer()`][seal_footer]) to detect corruption of the
+    /// length before it is used to locate the image. The status bits are excluded since they
+    /// change over the life of the image. An image's seal continues this CRC over the fields of
+    /// its extension which are fixed when it's flashed.
+    ///
+    /// [seal_footer]: struct.Image.html#method.seal_footer
+    pub fn metadata_crc(&self) -> u16 {
+        let mut crc = Crc::<16>::with_polynomial(crc::CRC16_POLYNOMIAL);
+        crc.update(&self.0.to_le_bytes()[..7]);
+        crc.checksum() as u16
+    }
+    /// Returns true if the footer is in the erased state of flash (all ones).
+    ///
+    /// Every status bit of an erased footer reads as unmarked, but its length and checksum are
+    /// meaningless, so such a footer denotes an empty slot rather than a pristine image.
+    pub fn is_erased(&self) -> bool {
+        self.0 == !0
+    }
+    /// Returns true if the footer is structurally sound.
+    ///
+    /// This is a cheap check, performed before the image's checksum is verified, which rejects
+    /// footers that cannot possibly describe a bootable image: those with a length of zero (the
+    /// checksum of an empty image is trivially valid and the image would start at its own
+    /// footer), those whose length extends beyond the start of the address space, and those
+    /// marked as having both succeeded and failed to boot. Every version is acceptable since the
+    /// entire range is usable.
+    pub fn sane(&self) -> bool {
+        self.length() != 0 && self.start_address().is_some() && !(self.success() && self.failure())
+    }
+    /// Returns true if the footer is marked both valid and invalid.
+    ///
+    /// The validity markers are set independently, so this can only arise from corruption of the
+    /// footer (or from a bug). Neither marker can be trusted and the image is never booted, even
+    /// if it has been marked as having successfully booted.
+    pub fn is_corrupt(&self) -> bool {
+        self.valid() && self.invalid()
+    }
+    /// Returns the address to the start of the image.
+    pub fn start_address(&self) -> Option<u32> {
+        (self as *const Footer as u32).checked_sub(self.length())
+    }
+}
+
+impl fmt::Debug for Footer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Footer")
+            .field("checksum", &format_args!("{:#08x}", self.checksum()))
+            .field("version", &self.version())
+            .field("length", &self.length())
+            .field("valid", &self.valid())
+            .field("invalid", &self.invalid())
+            .field("success", &self.success())
+            .field("failure", &self.failure())
+            .field("attempts", &self.attempts())
+            .finish()
+    }
+}
+
+/// The immutable fields identifying an image (see [`Image::identity()`][identity]).
+///
+/// Two identities are equal if the images have the same version, length, and checksum. Images
+/// which only differ in their status therefore have equal identities, while two builds of the
+/// same version generally don't; the images' own comparisons only consider the version.
+///
+/// [identity]: struct.Image.html#method.identity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ImageIdentity {
+    /// The version of the image.
+    pub version: u8,
+    /// The length (in bytes) of the image.
+    pub length: u32,
+    /// The checksum of the image.
+    pub checksum: u32,
+}
+
+/// The checksums of an image, as found by [`Image::verify_detail()`][verify_detail].
+///
+/// [verify_detail]: struct.Image.html#method.verify_detail
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyDetail {
+    /// The checksum recorded in the footer, and in the extension for a 32-bit checksum.
+    pub stored: u32,
+    /// The checksum computed over the image's contents, or `None` if it couldn't be computed
+    /// (because the start address is invalid or the CRC algorithm is unknown).
+    pub computed: Option<u32>,
+    /// The length (in bytes) of the region over which the checksum was computed.
+    pub length: u32,
+    /// The address of the start of that region, or `None` if it would precede the start of the
+    /// address space.
+    pub start: Option<u32>,
+}
+
+impl VerifyDetail {
+    /// Returns true if the computed checksum matches the stored one.
+    pub fn matches(&self) -> bool {
+        self.computed == Some(self.stored)
+    }
+}
+
+/// The decoded fields of a [`Footer`][footer].
+///
+/// [footer]: struct.Footer.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FooterInfo {
+    /// The checksum of the image.
+    pub checksum: u32,
+    /// The version of the image.
+    pub version: u8,
+    /// The length (in bytes) of the image.
+    pub length: u32,
+    /// True if the image has been marked valid.
+    pub valid: bool,
+    /// True if the image has been marked invalid.
+    pub invalid: bool,
+    /// True if the image has been marked as having successfully booted.
+    pub success: bool,
+    /// True if the image has been marked as having failed to boot.
+    pub failure: bool,
+    /// The number of remaining boot attempts.
+    pub attempts: usize,
+}
+
+/// The status markers of a footer, as encoded by [`Footer::encode_status()`][encode_status].
+///
+/// [encode_status]: struct.Footer.html#method.encode_status
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatusFlags {
+    /// True if the image has been marked valid.
+    pub valid: bool,
+    /// True if the image has been marked invalid.
+    pub invalid: bool,
+    /// True if the image has been marked as having successfully booted.
+    pub success: bool,
+    /// True if the image has been marked as having failed to boot.
+    pub failure: bool,
+}
+
+impl StatusFlags {
+    fn bits(&self) -> u8 {
+        self.valid as u8
+            | (self.invalid as u8) << 1
+            | (self.success as u8) << 2
+            | (self.failure as u8) << 3
+    }
+    fn from_bits(bits: u8) -> StatusFlags {
+        StatusFlags {
+            valid: bits & 1 != 0,
+            invalid: bits & 1 << 1 != 0,
+            success: bits & 1 << 2 != 0,
+            failure: bits & 1 << 3 != 0,
+        }
+    }
+}
+
+/// The boot status of an image, as determined by [`evaluate_footer()`][evaluate_footer] or
+/// reported by [`select_verbose()`][select_verbose].
+///
+/// [evaluate_footer]: fn.evaluate_footer.html
+/// [select_verbose]: fn.select_verbose.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootStatus {
+    /// The footer is erased; the slot is empty.
+    Erased,
+    /// The footer cannot describe a bootable image (see [`Footer::sane()`][sane]).
+    ///
+    /// [sane]: struct.Footer.html#method.sane
+    Unsound,
+    /// The image has been marked both valid and invalid (see
+    /// [`Footer::is_corrupt()`][is_corrupt]).
+    ///
+    /// [is_corrupt]: struct.Footer.html#method.is_corrupt
+    Corrupt,
+    /// The image has been marked invalid.
+    Invalid,
+    /// The image's checksum doesn't match its contents.
+    ChecksumInvalid,
+    /// The image has been marked as having failed to boot.
+    Failed,
+    /// The image is valid but has no remaining boot attempts.
+    Exhausted,
+    /// The image is valid and has boot attempts remaining.
+    Trial,
+    /// The image has been marked as having successfully booted.
+    Confirmed,
+}
+
+impl BootStatus {
+    /// Returns true if an image with this status can be booted.
+    pub fn is_bootable(&self) -> bool {
+        matches!(*self, BootStatus::Trial | BootStatus::Confirmed)
+    }
+}
+
+bitfield!{
+    /// The optional extension to the footer of a bootable image.
+    ///
+    /// The extension holds additional status for the image. If present, it must immediately
+    /// follow the image's footer and should be initialized with 1s when the image is flashed. It
+    /// spans two words: the first mostly holds what is recorded when the image is flashed, the
+    /// second the markers recorded as it is booted, along with the upper bits of a 32-bit
+    /// checksum.
+    ///
+    /// As in the footer, the markers are negated so that setting one only clears a bit. The
+    /// `clear_*()` methods, `increment_write_count()`, `reset_extra_attempts()`,
+    /// `reset_status()`, and re-recording a field which has already been recorded set bits, and
+    /// so require the flash to be erased.
+    pub struct Extension([u64; 2]);
+    no default BitRange;
+
+    n_booting, set_n_booting: 0;
+    n_prefer_other, set_n_prefer_other: 1;
+    n_writing, set_n_writing: 2;
+    n_footer_crc, set_n_footer_crc: 3;
+    n_recovery, set_n_recovery: 4;
+    u8, n_crc_algo, set_n_crc_algo: 6, 5;
+    n_grace, set_n_grace: 7;
+    u8, n_length_hi, set_n_length_hi: 15, 8;
+    /// Returns the board revision required by the image, or `HW_REV_ANY`.
+    pub u8, hw_rev, set_hw_rev: 23, 16;
+    u8, n_min_bootloader, set_n_min_bootloader: 31, 24;
+    u16, footer_crc_raw, set_footer_crc_raw: 47, 32;
+    u16, n_write_count, set_n_write_count: 63, 48;
+    n_started, set_n_started: 64;
+    n_swap_taken, set_n_swap_taken: 65;
+    n_grace_used, set_n_grace_used: 66;
+    n_forgiven, set_n_forgiven: 67;
+    u8, n_extra_attempts, set_n_extra_attempts: 71, 68;
+    u8, raw_checksum_hi, set_raw_checksum_hi: 79, 72;
+    u32, flashed_fields, _: 31, 4;
+}
+
+// No field spans the two words, so each is read and written within the word holding it.
+impl<T> BitRange<T> for Extension
+where
+    u64: BitRange<T>,
+{
+    fn bit_range(&self, msb: usize, lsb: usize) -> T {
+        self.0[lsb / 64].bit_range(msb % 64, lsb % 64)
+    }
+    fn set_bit_range(&mut self, msb: usize, lsb: usize, value: T) {
+        self.0[lsb / 64].set_bit_range(msb % 64, lsb % 64, value)
+    }
+}
+
+impl Extension {
+    /// The number of boot attempts an extension records beyond those in the footer.
+    ///
+    /// These are only used by images whose budget exceeds [`Footer::MAX_ATTEMPTS`][max] (see
+    /// [`Image`][image]).
+    ///
+    /// [max]: struct.Footer.html#associatedconstant.MAX_ATTEMPTS
+    /// [image]: struct.Image.html
+    pub const EXTRA_ATTEMPTS: usize = 4;
+    /// The largest count recorded by [`write_count()`][write_count].
+    ///
+    /// [write_count]: #method.write_count
+    pub const MAX_WRITE_COUNT: u32 = 0xFFFF;
+
+    /// Returns true if this extension can be reached from `prior` by clearing bits alone.
+    pub fn only_clears(&self, prior: &Extension) -> bool {
+        self.0
+            .iter()
+            .zip(&prior.0)
+            .all(|(word, prior)| word & !prior == 0)
+    }
+    /// Returns true if the image's booting marker is set.
+    ///
+    /// The marker stays set once the image has started (see [`started()`][started]), so that
+    /// starting only clears a bit.
+    ///
+    /// [started]: #method.started
+    pub fn booting(&self) -> bool {
+        !self.n_booting()
+    }
+    /// Sets the image's booting marker.
+    pub fn set_booting(&mut self) {
+        self.set_n_booting(false)
+    }
+    /// Returns true if the image has recorded that it started after its booting marker was set.
+    pub fn started(&self) -> bool {
+        !self.n_started()
+    }
+    /// Records that the image has started.
+    pub fn set_started(&mut self) {
+        self.set_n_started(false)
+    }
+    /// Returns true if the image has requested that its sibling be preferred.
+    pub fn prefer_other(&self) -> bool {
+        !self.n_prefer_other()
+    }
+    /// Requests that the image's sibling be preferred.
+    pub fn set_prefer_other(&mut self) {
+        self.set_n_prefer_other(false)
+    }
+    /// Returns true if the image's request that its sibling be preferred has been consumed.
+    pub fn swap_taken(&self) -> bool {
+        !self.n_swap_taken()
+    }
+    /// Records that the image's request that its sibling be preferred has been consumed.
+    pub fn set_swap_taken(&mut self) {
+        self.set_n_swap_taken(false)
+    }
+    /// Returns true if the image is in the process of being written.
+    pub fn writing(&self) -> bool {
+        !self.n_writing()
+    }
+    /// Marks the image as being in the process of being written.
+    pub fn set_writing(&mut self) {
+        self.set_n_writing(false)
+    }
+    /// Clears the image's writing marker.
+    pub fn clear_writing(&mut self) {
+        self.set_n_writing(true)
+    }
+    /// Returns the CRC of the footer's metadata, if one has been recorded.
+    ///
+    /// See [`Image::seal_footer()`][seal_footer], which also covers the fields of the extension
+    /// recorded when the image is flashed.
+    ///
+    /// [seal_footer]: struct.Image.html#method.seal_footer
+    pub fn footer_crc(&self) -> Option<u16> {
+        if self.n_footer_crc() {
+            None
+        } else {
+            Some(self.footer_crc_raw())
+        }
+    }
+    /// Records the CRC of the footer's metadata.
+    pub fn set_footer_crc(&mut self, crc: u16) {
+        self.set_footer_crc_raw(crc);
+        self.set_n_footer_crc(false)
+    }
+    /// Returns true if the image is a recovery image, to be booted only as a last resort.
+    pub fn recovery(&self) -> bool {
+        !self.n_recovery()
+    }
+    /// Marks the image as a recovery image.
+    ///
+    /// This is intended to be done when the image is flashed; the bootloader never changes it.
+    pub fn set_recovery(&mut self) {
+        self.set_n_recovery(false)
+    }
+    /// Returns the CRC algorithm of the image's checksum, [`CRC_ALGO_SWITCHER`][switcher] unless
+    /// recorded.
+    ///
+    /// The field is two bits wide, so values up to three can be recorded. The footer records the
+    /// low 24 bits of the checksum, and a wider checksum's upper bits are recorded by
+    /// [`set_checksum_hi()`][checksum_hi] (see [`CRC_ALGO_IEEE`][ieee]).
+    ///
+    /// [switcher]: constant.CRC_ALGO_SWITCHER.html
+    /// [checksum_hi]: #method.set_checksum_hi
+    /// [ieee]: constant.CRC_ALGO_IEEE.html
+    pub fn crc_algo(&self) -> u8 {
+        !self.n_crc_algo() & 0b11
+    }
+    /// Records the CRC algorithm of the image's checksum.
+    pub fn set_crc_algo(&mut self, algo: u8) {
+        self.set_n_crc_algo(!algo)
+    }
+    /// Returns the upper eight bits of a 32-bit checksum, which the footer can't hold.
+    ///
+    /// Like the footer's checksum, this is stored as is, so it reads as `0xFF` unless recorded.
+    /// It is only consulted for images whose algorithm yields a 32-bit checksum (see
+    /// [`crc_algo()`][crc_algo]).
+    ///
+    /// [crc_algo]: #method.crc_algo
+    pub fn checksum_hi(&self) -> u8 {
+        self.raw_checksum_hi()
+    }
+    /// Records the upper eight bits of a 32-bit checksum.
+    pub fn set_checksum_hi(&mut self, hi: u8) {
+        self.set_raw_checksum_hi(hi)
+    }
+    /// Returns true if the image has been granted a grace boot which hasn't yet been used.
+    ///
+    /// The first boot which would consume one of the image's attempts consumes the grace instead,
+    /// so that a slow first boot which resets the device (e.g. while migrating data) isn't held
+    /// against the image. Granting and using the grace are recorded by separate markers, so that
+    /// each only clears a bit.
+    pub fn grace(&self) -> bool {
+        !self.n_grace() && self.n_grace_used()
+    }
+    /// Grants the image a grace boot.
+    ///
+    /// This is intended to be done by the updater when staging the image.
+    pub fn set_grace(&mut self) {
+        self.set_n_grace(false)
+    }
+    /// Uses up the image's grace boot.
+    pub fn use_grace(&mut self) {
+        self.set_n_grace_used(false)
+    }
+    /// Returns true if the image's failure has been forgiven.
+    ///
+    /// This is recorded by [`select_with_failure_policy()`][policy] rather than by clearing the
+    /// footer's failure marker, which would set a bit. A forgiven image is treated as if it had
+    /// never failed.
+    ///
+    /// [policy]: fn.select_with_failure_policy.html
+    pub fn forgiven(&self) -> bool {
+        !self.n_forgiven()
+    }
+    /// Forgives the image's failure.
+    pub fn set_forgiven(&mut self) {
+        self.set_n_forgiven(false)
+    }
+    /// Returns the upper eight bits of the image's length, which are zero unless recorded.
+    ///
+    /// This extends the 24-bit length recorded in the footer to 32 bits, so that images of
+    /// 16 MiB or more can be described. Such images must be flashed with an extension and be
+    /// created with `Image::from_extended()`; their footer is otherwise unchanged.
+    pub fn length_hi(&self) -> u8 {
+        !self.n_length_hi()
+    }
+    /// Records the upper eight bits of the image's length.
+    pub fn set_length_hi(&mut self, hi: u8) {
+        self.set_n_length_hi(!hi)
+    }
+    /// Returns the oldest bootloader version which can start the image, or zero if any can.
+    pub fn min_bootloader(&self) -> u8 {
+        !self.n_min_bootloader()
+    }
+    /// Records the oldest bootloader version which can start the image.
+    pub fn set_min_bootloader(&mut self, version: u8) {
+        self.set_n_min_bootloader(!version)
+    }
+    /// Returns the number of times the footer has been written.
+    pub fn write_count(&self) -> u32 {
+        u32::from(!self.n_write_count())
+    }
+    /// Increments the number of times the footer has been written, saturating at
+    /// [`MAX_WRITE_COUNT`][max].
+    ///
+    /// [max]: #associatedconstant.MAX_WRITE_COUNT
+    pub fn increment_write_count(&mut self) {
+        let count = self.n_write_count().saturating_sub(1);
+        self.set_n_write_count(count)
+    }
+    /// Returns the number of extra boot attempts remaining.
+    ///
+    /// Like the footer's, these are stored as a thermometer code.
+    pub fn extra_attempts(&self) -> usize {
+        self.n_extra_attempts().count_ones() as usize
+    }
+    /// Decrements the number of extra boot attempts by one, unless they've been exhausted.
+    pub fn decrement_extra_attempts(&mut self) {
+        let attempts = self.n_extra_attempts() << 1;
+        self.set_n_extra_attempts(attempts)
+    }
+    /// Restores the extra boot attempts to [`EXTRA_ATTEMPTS`][extra].
+    ///
+    /// [extra]: #associatedconstant.EXTRA_ATTEMPTS
+    pub fn reset_extra_attempts(&mut self) {
+        self.set_n_extra_attempts(!0)
+    }
+    /// Restores the extension's status to the pristine state in which it was flashed.
+    ///
+    /// The booting, started, swap, writing, grace used, and forgiven markers are cleared, the
+    /// write count is reset, and the extra boot attempts are restored to
+    /// [`EXTRA_ATTEMPTS`][extra]. The fields recorded when the image was flashed (including the
+    /// grant of its grace) and the CRC sealing its footer describe the image itself and are left
+    /// untouched. Like [`Footer::reset_status()`][reset_status], this sets bits.
+    ///
+    /// [extra]: #associatedconstant.EXTRA_ATTEMPTS
+    /// [reset_status]: struct.Footer.html#method.reset_status
+    pub fn reset_status(&mut self) {
+        self.set_n_booting(true);
+        self.set_n_started(true);
+        self.set_n_prefer_other(true);
+        self.set_n_swap_taken(true);
+        self.set_n_writing(true);
+        self.set_n_grace_used(true);
+        self.set_n_forgiven(true);
+        self.set_n_write_count(!0);
+        self.reset_extra_attempts()
+    }
+}
+
+bitfield!{
+    /// Information about how an image was built, recorded when it is flashed.
+    ///
+    /// If present, it must immediately follow the image's extension. Unlike the footer and the
+    /// extension, it is never written by the bootloader. It isn't covered by the image's
+    /// checksum, so it can be recorded without affecting the image's verification; the bits that
+    /// aren't yet used should be left erased.
+    pub struct BuildInfo(u64);
+
+    u32, raw_build_time, _: 31, 0;
+    n_digest, _: 32;
+}
+
+impl BuildInfo {
+    /// The value of an unrecorded build time.
+    const NO_BUILD_TIME: u32 = !0;
+
+    /// Returns the time at which the image was built, as a Unix timestamp, or zero if unknown.
+    pub fn build_time(&self) -> u32 {
+        match self.raw_build_time() {
+            BuildInfo::NO_BUILD_TIME => 0,
+            time => time,
+        }
+    }
+    /// Returns true if the image ends with a SHA-256 digest (see `Image::has_digest()`).
+    pub fn has_digest(&self) -> bool {
+        !self.n_digest()
+    }
+}
+
+bitfield!{
+    /// The bootloader's state which is shared by all images, kept in a dedicated word of flash.
+    ///
+    /// Rather than being duplicated across the footers of the images, this records which slot is
+    /// active, the anti-rollback counter, the number of consecutive unconfirmed boots of the
+    /// active slot, and which slot (if any) has been found stuck in a boot loop. It should be
+    /// initialized with 1s, which is the state of a device which hasn't yet booted an image. See
+    /// [`select_with_state()`][select_with_state].
+    ///
+    /// [select_with_state]: fn.select_with_state.html
+    pub struct BootState(u64);
+
+    u8, raw_active_slot, set_raw_active_slot: 7, 0;
+    u8, n_rollback_counter, set_n_rollback_counter: 15, 8;
+    u8, n_boot_loop_count, set_n_boot_loop_count: 23, 16;
+    u8, raw_stuck_slot, set_raw_stuck_slot: 31, 24;
+    u8, raw_stuck_version, set_raw_stuck_version: 39, 32;
+}
+
+impl BootState {
+    /// The value of an unrecorded active slot.
+    const NO_ACTIVE_SLOT: u8 = !0;
+
+    /// Creates a view of the boot state at the given address.
+    ///
+    /// # Safety
+    ///
+    /// The address must point to the word of flash which holds the state, which must not be
+    /// accessed by other means for the lifetime of the returned reference.
+    pub unsafe fn from<'a>(addr: u32) -> &'a mut BootState {
+        (addr as *mut BootState)
+            .as_mut()
+            .unwrap_or_else(|| fatal("boot state address is null"))
+    }
+    /// Interprets the given word of flash as the boot state.
+    pub fn from_bits(bits: u64) -> BootState {
+        BootState(bits)
+    }
+    /// Returns the raw 64-bit value of the state.
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+    /// Returns the position of the image which was last selected, if any.
+    pub fn active_slot(&self) -> Option<u8> {
+        match self.raw_active_slot() {
+            BootState::NO_ACTIVE_SLOT => None,
+            slot => Some(slot),
+        }
+    }
+    /// Records the position of the image which has been selected.
+    ///
+    /// Slots are numbered from zero; slot 255 can't be recorded, since it reads as erased.
+    pub fn set_active_slot(&mut self, slot: u8) {
+        self.set_raw_active_slot(slot)
+    }
+    /// Returns the oldest version which may be booted.
+    pub fn rollback_counter(&self) -> u8 {
+        !self.n_rollback_counter()
+    }
+    /// Raises the oldest version which may be booted to the given version.
+    ///
+    /// The counter never decreases; a version below the current counter is ignored.
+    pub fn raise_rollback_counter(&mut self, version: u8) {
+        if version > self.rollback_counter() {
+            self.set_n_rollback_counter(!version)
+        }
+    }
+    /// Returns the number of consecutive boots of the active slot without it being confirmed.
+    pub fn boot_loop_count(&self) -> u8 {
+        !self.n_boot_loop_count()
+    }
+    /// Counts another boot, saturating at 255.
+    pub fn increment_boot_loop_count(&mut self) {
+        let count = self.n_boot_loop_count().saturating_sub(1);
+        self.set_n_boot_loop_count(count)
+    }
+    /// Resets the number of consecutive unconfirmed boots.
+    pub fn reset_boot_loop_count(&mut self) {
+        self.set_n_boot_loop_count(!0)
+    }
+    /// Returns the position of the image which was found to be stuck in a boot loop, if any.
+    pub fn stuck_slot(&self) -> Option<u8> {
+        match self.raw_stuck_slot() {
+            BootState::NO_ACTIVE_SLOT => None,
+            slot => Some(slot),
+        }
+    }
+    /// Returns the version of the image which was found to be stuck in a boot loop.
+    ///
+    /// This is only meaningful if a stuck slot has been recorded (see
+    /// [`stuck_slot()`][stuck_slot]).
+    ///
+    /// [stuck_slot]: #method.stuck_slot
+    pub fn stuck_version(&self) -> u8 {
+        self.raw_stuck_version()
+    }
+    /// Records that the image of the given version, in the given slot, is stuck in a boot loop.
+    pub fn set_stuck_slot(&mut self, slot: u8, version: u8) {
+        self.set_raw_stuck_slot(slot);
+        self.set_raw_stuck_version(version)
+    }
+    /// Forgets the image which was stuck in a boot loop.
+    pub fn clear_stuck_slot(&mut self) {
+        self.set_raw_stuck_slot(BootState::NO_ACTIVE_SLOT);
+        self.set_raw_stuck_version(!0)
+    }
+}
+
+impl fmt::Debug for BootState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BootState")
+            .field("active_slot", &self.active_slot())
+            .field("rollback_counter", &self.rollback_counter())
+            .field("boot_loop_count", &self.boot_loop_count())
+            .field("stuck_slot", &self.stuck_slot())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Extension")
+            .field("booting", &self.booting())
+            .field("started", &self.started())
+            .field("prefer_other", &self.prefer_other())
+            .field("swap_taken", &self.swap_taken())
+            .field("writing", &self.writing())
+            .field("footer_crc", &self.footer_crc())
+            .field("recovery", &self.recovery())
+            .field("crc_algo", &self.crc_algo())
+            .field("checksum_hi", &self.checksum_hi())
+            .field("grace", &self.grace())
+            .field("forgiven", &self.forgiven())
+            .field("hw_rev", &self.hw_rev())
+            .field("length_hi", &self.length_hi())
+            .field("min_bootloader", &self.min_bootloader())
+            .field("write_count", &self.write_count())
+            .field("extra_attempts", &self.extra_attempts())
+            .finish()
+    }
+}
+
+/// Returns the in-memory representation of the given value.
+fn bytes<T>(value: &T) -> &[u8] {
+    unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "fatal-hook")]
+    use std::cell::Cell;
+    use testing::MockCrcEngine;
+
+    /// A buffer, aligned like flash, holding an image followed by its footer.
+    #[repr(C, align(8))]
+    pub struct Flash<const N: usize>(pub [u8; N]);
+
+    impl<const N: usize> Flash<N> {
+        /// Lays out the given contents, followed by a pristine footer of the given version with
+        /// the contents' checksum, at the end of the buffer.
+        pub fn new(contents: &[u8], version: u8) -> Flash<N> {
+            let mut flash = Flash([0xFF; N]);
+            let footer = N - mem::size_of::<Footer>();
+            flash.0[footer - contents.len()..footer].copy_from_slice(contents);
+
+            let value = 0xFF << 56
+                | (contents.len() as u64) << 32
+                | u64::from(version) << 24
+                | u64::from(crc::calculate(contents));
+            flash.0[footer..].copy_from_slice(&value.to_le_bytes());
+            flash
+        }
+
+        /// Returns the footer at the end of the buffer.
+        pub fn footer(&self) -> Footer {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&self.0[N - mem::size_of::<Footer>()..]);
+            Footer(u64::from_le_bytes(bytes))
+        }
+    }
+
+    /// Returns an image, without an extension, over the given footer.
+    pub fn image(footer: &mut Footer) -> Image<'_> {
+        Image {
+            footer,
+            extension: None,
+            build_info: None,
+            cached_crc: None,
+            index: 0,
+        }
+    }
+
+    /// Returns an image over the given footer and extension.
+    pub fn extended<'a>(footer: &'a mut Footer, extension: &'a mut Extension) -> Image<'a> {
+        Image {
+            footer,
+            extension: Some(extension),
+            build_info: None,
+            cached_crc: None,
+            index: 0,
+        }
+    }
+
+    /// A flash writer which records the writes made through it.
+    #[derive(Default)]
+    pub struct MockWriter {
+        pub writes: usize,
+        pub last_addr: u32,
+        pub last_len: usize,
+    }
+
+    impl FlashWriter for MockWriter {
+        fn write(&mut self, addr: u32, data: &[u8]) {
+            self.writes += 1;
+            self.last_addr = addr;
+            self.last_len = data.len();
+        }
+    }
+
+    /// A writer over a buffer which stands in for the flash at `base`.
+    pub struct BufferWriter<'a> {
+        pub base: u32,
+        pub buffer: &'a mut [u8],
+    }
+
+    impl<'a> FlashWriter for BufferWriter<'a> {
+        fn write(&mut self, addr: u32, data: &[u8]) {
+            let offset = (addr - self.base) as usize;
+            self.buffer[offset..offset + data.len()].copy_from_slice(data);
+        }
+    }
+
+    /// Returns a freshly-flashed footer (all status bits unmarked) with the given version.
+    fn footer(version: u8) -> Footer {
+        Footer(0xFF << 56 | 4 << 32 | u64::from(version) << 24)
+    }
+
+    /// Returns a footer with the given version which has already been marked valid, so that
+    /// selecting its image doesn't compute a checksum over the memory preceding it.
+    fn verified(version: u8) -> Footer {
+        let mut footer = footer(version);
+        footer.set_valid();
+        footer
+    }
+
+    #[test]
+    fn test_footer_address() {
+        let mut footer = footer(1);
+        let addr = &footer as *const Footer as u32;
+        let image = image(&mut footer);
+
+        assert_eq!(image.footer_address(), addr);
+        assert_eq!(image.footer().start_address(), Some(addr - 4));
+    }
+
+    #[test]
+    fn test_mark_invalid() {
+        let mut footer_a = footer(2);
+        let mut footer_b = footer(1);
+        footer_b.set_success();
+        let mut image_a = image(&mut footer_a);
+        let mut image_b = image(&mut footer_b);
+
+        image_a.mark_invalid();
+        assert!(image_a.footer.invalid());
+        assert!(image_a.footer.attempts() > 0);
+        assert!(!image_a.verify_bootable());
+        assert_eq!(
+            select(&mut image_a, &mut image_b).map(|image| image.footer.version()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_select_by() {
+        let mut footer_a = footer(1);
+        footer_a.set_success();
+        footer_a.set_n_attempts(0);
+        let mut footer_b = footer(1);
+        footer_b.set_valid();
+        let mut footer_c = footer(0);
+        footer_c.set_valid();
+        let mut images = [
+            image(&mut footer_a),
+            image(&mut footer_b),
+            image(&mut footer_c),
+        ];
+
+        let selected = select_by(&mut images, |a, b| {
+            a.cmp(b)
+                .then(a.footer().attempts().cmp(&b.footer().attempts()))
+        });
+        assert_eq!(selected.map(|image| image.footer().attempts()), Some(4));
+
+        images.swap(0, 1);
+        let selected = select_by(&mut images, |a, b| {
+            a.cmp(b)
+                .then(a.footer().attempts().cmp(&b.footer().attempts()))
+        });
+        assert_eq!(selected.map(|image| image.footer().attempts()), Some(4));
+
+        let selected = select_by(&mut images, |a, b| b.cmp(a));
+        assert_eq!(selected.map(|image| image.footer().version()), Some(0));
+    }
+
+    #[test]
+    fn test_verify_bootable_success_and_failure() {
+        let mut footer = footer(1);
+        footer.set_success();
+        footer.set_failure();
+        assert!(!footer.sane());
+
+        let mut image = image(&mut footer);
+        assert!(!image.verify_bootable());
+        assert!(image.footer().invalid());
+        assert!(!image.footer().valid());
+    }
+
+    #[test]
+    fn test_verify_bootable_zero_length() {
+        let mut footer = Footer(0xFF00_0000_0100_0000);
+        assert_eq!(footer.length(), 0);
+        assert!(!footer.sane());
+        assert!(!image(&mut footer).verify_bootable());
+        assert!(footer.invalid());
+
+        let mut footer = Footer(0xFF00_0000_0100_0000);
+        footer.set_success();
+        assert!(!image(&mut footer).verify_bootable());
+        assert!(footer.invalid());
+    }
+
+    #[test]
+    fn test_verify_bootable_erased() {
+        assert!(!footer(1).is_erased());
+
+        let mut footer = Footer(!0);
+        assert!(footer.is_erased());
+
+        let mut engine = MockCrcEngine::returning(0);
+        let mut image = image(&mut footer);
+        assert!(!image.is_bootable());
+        assert!(!image.verify_bootable_using(&mut engine));
+        assert_eq!(image.cached_result(), None);
+        assert_eq!(engine.runs(), 0);
+        assert!(image.footer().is_erased());
+    }
+
+    #[test]
+    fn test_sane() {
+        let mut confirmed = footer(1);
+        assert!(confirmed.sane());
+        confirmed.set_success();
+        assert!(confirmed.sane());
+
+        let mut failed = footer(1);
+        failed.set_failure();
+        assert!(failed.sane());
+    }
+
+    #[test]
+    fn test_select_by_none() {
+        let mut footer_a = footer(1);
+        footer_a.set_failure();
+        let mut footer_b = footer(2);
+        footer_b.set_invalid();
+        let mut images = [image(&mut footer_a), image(&mut footer_b)];
+
+        assert!(select_by(&mut images, |a, b| a.cmp(b)).is_none());
+        assert!(select_by(&mut [] as &mut [Image], |a, b| a.cmp(b)).is_none());
+    }
+
+    #[test]
+    fn test_decrement_attempts() {
+        let mut footer = footer(1);
+        assert_eq!(footer.attempts(), Footer::MAX_ATTEMPTS);
+
+        for attempts in (0..Footer::MAX_ATTEMPTS).rev() {
+            footer.decrement_attempts();
+            assert_eq!(footer.attempts(), attempts);
+        }
+
+        footer.decrement_attempts();
+        assert_eq!(footer.attempts(), 0);
+    }
+
+    #[test]
+    fn test_mark_success() {
+        let mut footer = footer(1);
+        for _ in 1..Footer::MAX_ATTEMPTS {
+            footer.decrement_attempts();
+        }
+        assert_eq!(footer.attempts(), 1);
+
+        let mut image = image(&mut footer);
+        image.mark_success();
+        assert!(image.footer().success());
+        assert_eq!(image.attempts(), Footer::MAX_ATTEMPTS);
+        // The refill is implied by the success marker; the footer's count is left alone.
+        assert_eq!(image.footer().attempts(), 1);
+        assert!(image.verify_bootable());
+    }
+
+    #[test]
+    fn test_mark_success_exhausted() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        footer.set_n_attempts(0);
+
+        let mut image = image(&mut footer);
+        assert!(!image.verify_bootable());
+        image.mark_success();
+        assert_eq!(image.attempts(), Footer::MAX_ATTEMPTS);
+        assert!(image.verify_bootable());
+    }
+
+    #[test]
+    fn test_prepare_boot() {
+        let mut footer = footer(1);
+        let mut image = image(&mut footer);
+
+        image.prepare_boot();
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS - 1);
+
+        image.mark_success();
+        image.prepare_boot();
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS - 1);
+        assert_eq!(image.attempts(), Footer::MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_optimistic_boot() {
+        let mut footer = footer(1);
+        let mut extension = Extension([!0; 2]);
+        let mut image = extended(&mut footer, &mut extension);
+
+        image.prepare_optimistic_boot();
+        assert!(image.was_booting());
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS);
+
+        image.mark_started();
+        assert!(!image.was_booting());
+        assert!(image.extension.as_ref().unwrap().started());
+        assert!(!image.footer().success());
+
+        image.mark_success();
+        assert!(!image.was_booting());
+        assert!(image.footer().success());
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS);
+
+        image.prepare_optimistic_boot();
+        assert!(!image.was_booting());
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_optimistic_boot_reset() {
+        let mut footer = footer(1);
+        let mut extension = Extension([!0; 2]);
+        let mut image = extended(&mut footer, &mut extension);
+
+        image.prepare_optimistic_boot();
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS);
+
+        // The image resets before it has started.
+        image.prepare_optimistic_boot();
+        assert!(image.was_booting());
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS - 1);
+
+        image.mark_started();
+        assert!(!image.was_booting());
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS - 1);
+
+        // Having started, the image's later boots consume their attempts up front.
+        image.prepare_optimistic_boot();
+        assert!(!image.was_booting());
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS - 2);
+    }
+
+    #[test]
+    fn test_optimistic_boot_without_extension() {
+        let mut footer = footer(1);
+        let mut image = image(&mut footer);
+
+        image.prepare_optimistic_boot();
+        image.mark_started();
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS - 1);
+    }
+
+    #[test]
+    fn test_describe() {
+        let footer = Footer(0xFF00_1234_0598_7654);
+        assert_eq!(
+            footer.describe(),
+            FooterInfo {
+                checksum: 0x98_7654,
+                version: 0x05,
+                length: 0x1234,
+                valid: false,
+                invalid: false,
+                success: false,
+                failure: false,
+                attempts: Footer::MAX_ATTEMPTS,
+            }
+        );
+
+        let footer = Footer(0x3A00_1234_0598_7654);
+        assert_eq!(
+            footer.describe(),
+            FooterInfo {
+                checksum: 0x98_7654,
+                version: 0x05,
+                length: 0x1234,
+                valid: true,
+                invalid: false,
+                success: true,
+                failure: false,
+                attempts: 2,
+            }
+        );
+
+        let footer = Footer(0x0500_0001_FF00_0000);
+        assert_eq!(
+            footer.describe(),
+            FooterInfo {
+                checksum: 0,
+                version: 0xFF,
+                length: 1,
+                valid: false,
+                invalid: true,
+                success: false,
+                failure: true,
+                attempts: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_survey() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_invalid();
+        let mut footer_c = footer(3);
+        footer_c.set_success();
+        footer_c.set_n_attempts(0);
+        let bits = [footer_a.0, footer_b.0, footer_c.0];
+
+        let images = [
+            image(&mut footer_a),
+            image(&mut footer_b),
+            image(&mut footer_c),
+        ];
+        let bootable = survey(&images);
+        assert_eq!(bootable, [true, false, true]);
+        for (bootable, image) in bootable.iter().zip(&images) {
+            assert_eq!(*bootable, image.is_bootable());
+        }
+        for (bits, image) in bits.iter().zip(&images) {
+            assert_eq!(*bits, image.footer().0);
+        }
+
+        assert_eq!(survey::<0, 4>(&[]), []);
+    }
+
+    #[test]
+    fn test_is_bootable() {
+        let mut exhausted = footer(1);
+        exhausted.set_valid();
+        exhausted.set_n_attempts(0);
+        assert!(!image(&mut exhausted).is_bootable());
+
+        let mut unsound = footer(1);
+        unsound.set_success();
+        unsound.set_failure();
+        assert!(!image(&mut unsound).is_bootable());
+        assert!(!unsound.invalid());
+    }
+
+    #[test]
+    fn test_is_newer_than() {
+        for &(a, b) in &[(2, 1), (1, 2), (1, 1), (0, 255)] {
+            let mut footer_a = footer(a);
+            let mut footer_b = footer(b);
+            let image_a = image(&mut footer_a);
+            let image_b = image(&mut footer_b);
+
+            assert_eq!(image_a.is_newer_than(&image_b), a > b);
+            assert_eq!(image_a.is_newer_than(&image_b), image_a > image_b);
+            assert_eq!(image_a.is_older_than(&image_b), a < b);
+            assert_eq!(image_a.is_older_than(&image_b), image_a < image_b);
+            assert_eq!(
+                image_a.is_newer_than(&image_b),
+                image_b.is_older_than(&image_a)
+            );
+        }
+    }
+
+    #[test]
+    fn test_cmp_wrapping() {
+        for &(a, b, ordering) in &[
+            (255, 0, Ordering::Less),
+            (254, 255, Ordering::Less),
+            (0, 200, Ordering::Less),
+            (0, 0, Ordering::Equal),
+            (255 - VERSION_WRAP_WINDOW + 1, 0, Ordering::Less),
+            (255 - VERSION_WRAP_WINDOW, 0, Ordering::Greater),
+        ] {
+            let mut footer_a = footer(a);
+            let mut footer_b = footer(b);
+            let image_a = image(&mut footer_a);
+            let image_b = image(&mut footer_b);
+            assert_eq!(image_a.cmp_wrapping(&image_b), ordering, "{} vs {}", a, b);
+            assert_eq!(
+                image_b.cmp_wrapping(&image_a),
+                ordering.reverse(),
+                "{} vs {}",
+                b,
+                a
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_and_set_checksum() {
+        let contents = *b"switcher";
+        let mut footer = Footer(!(0xFF_FFFF << 32) | (contents.len() as u64) << 32);
+        footer.compute_and_set_checksum(&contents);
+        assert_eq!(footer.checksum(), crc::calculate(&contents));
+
+        // The checksum is stored, rather than merged with whatever was recorded before.
+        let mut fresh = Footer::new(1, contents.len() as u32, 0);
+        fresh.compute_and_set_checksum(&contents);
+        assert_eq!(fresh.checksum(), crc::calculate(&contents));
+        let mut stale = Footer::new(1, contents.len() as u32, !crc::calculate(&contents));
+        stale.compute_and_set_checksum(&contents);
+        assert_eq!(stale.checksum(), crc::calculate(&contents));
+        assert_eq!(stale.bits(), fresh.bits());
+
+        let mut block = [0; 11];
+        block[..8].copy_from_slice(&contents);
+        block[8..].copy_from_slice(&footer.checksum().to_be_bytes()[1..]);
+        assert!(crc::is_valid(&block));
+
+        // The same footer, flashed after the image, verifies.
+        let mut flash = Flash::<24>([0xFF; 24]);
+        flash.0[8..16].copy_from_slice(&contents);
+        flash.0[16..].copy_from_slice(&footer.bits().to_le_bytes());
+        assert_eq!(
+            flash.footer().bits(),
+            Flash::<24>::new(&contents, 0xFF).footer().bits()
+        );
+        assert!(Image::from_slice(&mut flash.0).verify_bootable());
+    }
+
+    #[test]
+    fn test_select_address_tie_break() {
+        let mut footers = [verified(1), verified(1)];
+        let (low, high) = footers.split_at_mut(1);
+        let low_address = &low[0] as *const Footer as u32;
+        let mut image_low = image(&mut low[0]);
+        let mut image_high = image(&mut high[0]);
+
+        let selected = select(&mut image_low, &mut image_high).map(|image| image.footer_address());
+        assert_eq!(selected, Some(low_address));
+        let selected = select(&mut image_high, &mut image_low).map(|image| image.footer_address());
+        assert_eq!(selected, Some(low_address));
+
+        // A newer version still wins, regardless of its address.
+        image_high.footer.0 = verified(2).0;
+        let selected =
+            select(&mut image_low, &mut image_high).map(|image| image.footer().version());
+        assert_eq!(selected, Some(2));
+    }
+
+    #[test]
+    fn test_select_attempts_tie_break() {
+        let mut footers = [verified(1), verified(1)];
+        let (low, high) = footers.split_at_mut(1);
+        high[0].decrement_attempts();
+        high[0].decrement_attempts();
+        let mut image_low = image(&mut low[0]);
+        let mut image_high = image(&mut high[0]);
+
+        // The tie goes to the most attempts, before the lowest address is considered.
+        image_low.footer.decrement_attempts();
+        let selected = select(&mut image_low, &mut image_high).map(|image| image.attempts());
+        assert_eq!(selected, Some(3));
+        let selected = select(&mut image_high, &mut image_low).map(|image| image.attempts());
+        assert_eq!(selected, Some(3));
+
+        image_low.footer.0 = verified(1).0 & !(0b1110 << 60);
+        let selected = select(&mut image_low, &mut image_high).map(|image| image.attempts());
+        assert_eq!(selected, Some(2));
+        let selected = select(&mut image_high, &mut image_low).map(|image| image.attempts());
+        assert_eq!(selected, Some(2));
+
+        // A newer version still wins with fewer attempts.
+        image_low.footer.0 = verified(2).0 & !(0b1110 << 60);
+        let selected = select(&mut image_high, &mut image_low).map(|image| image.attempts());
+        assert_eq!(selected, Some(1));
+    }
+
+    #[test]
+    fn test_select_wrapping() {
+        for &(a, b, newest) in &[(255, 0, 0), (254, 255, 255), (0, 200, 200)] {
+            let mut footer_a = footer(a);
+            footer_a.set_valid();
+            let mut footer_b = footer(b);
+            footer_b.set_valid();
+            let mut images = [image(&mut footer_a), image(&mut footer_b)];
+
+            let selected = select_wrapping(&mut images);
+            assert_eq!(selected.map(|image| image.footer().version()), Some(newest));
+        }
+    }
+
+    #[test]
+    fn test_invalidate_cache() {
+        let mut footer = footer(1);
+        let blank = footer.0;
+        let mut image = image(&mut footer);
+
+        let mut engine = MockCrcEngine::returning(0);
+        assert!(image.verify_bootable_using(&mut engine));
+        image.footer.0 = blank;
+        assert!(image.verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 1);
+
+        image.invalidate_cache();
+        assert_eq!(image.cached_result(), None);
+        image.footer.0 = blank;
+        assert!(image.verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 2);
+
+        // A change of length also invalidates the cached CRC.
+        image.footer.0 = blank & !(0xFF_FFFF << 32) | 5 << 32;
+        assert_eq!(image.cached_result(), None);
+        assert!(image.verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 3);
+    }
+
+    #[test]
+    fn test_corrupt() {
+        // Valid, invalid, and successfully booted.
+        let bits = footer(1).0 & !(0b101 << 56);
+        let mut corrupt = Footer(bits & !(1 << 57));
+        assert!(corrupt.is_corrupt());
+        assert!(!footer(1).is_corrupt());
+        assert_eq!(evaluate_footer(corrupt.0, b"----"), BootStatus::Corrupt);
+        assert!(!BootStatus::Corrupt.is_bootable());
+
+        let before = corrupt.0;
+        let mut slot = image(&mut corrupt);
+        assert!(!slot.is_bootable());
+        assert!(!slot.verify_bootable());
+        assert_eq!(slot.footer().bits(), before);
+
+        let mut images = [slot];
+        let (selected, statuses) = select_verbose(&mut images);
+        assert!(selected.is_none());
+        assert_eq!(statuses, [BootStatus::Corrupt]);
+
+        // Without the corruption, the same footer is confirmed.
+        assert_eq!(evaluate_footer(bits, b"----"), BootStatus::Confirmed);
+    }
+
+    #[test]
+    fn test_build_time() {
+        let mut footer_a = verified(2);
+        let mut footer_b = verified(2);
+        let mut footer_c = verified(1);
+        let mut extension_a = Extension([!0; 2]);
+        let mut extension_b = Extension([!0; 2]);
+        let mut extension_c = Extension([!0; 2]);
+        let info_a = BuildInfo(!0 << 32 | 1_600_000_000);
+        let info_b = BuildInfo(!0 << 32 | 1_700_000_000);
+        let info_c = BuildInfo(!0 << 32 | 1_800_000_000);
+        let mut images = [
+            Image {
+                build_info: Some(&info_b),
+                ..extended(&mut footer_a, &mut extension_a)
+            },
+            Image {
+                build_info: Some(&info_a),
+                ..extended(&mut footer_b, &mut extension_b)
+            },
+            Image {
+                build_info: Some(&info_c),
+                ..extended(&mut footer_c, &mut extension_c)
+            },
+        ];
+        assert_eq!(images[0].build_time(), 1_700_000_000);
+        assert_eq!(images[1].build_time(), 1_600_000_000);
+
+        // The newest version wins, then the newest build of it.
+        let selected = select_by(&mut images, Image::cmp_build_time);
+        assert_eq!(
+            selected.map(|image| image.build_time()),
+            Some(1_700_000_000)
+        );
+        images.swap(0, 1);
+        let selected = select_by(&mut images, Image::cmp_build_time);
+        assert_eq!(
+            selected.map(|image| image.build_time()),
+            Some(1_700_000_000)
+        );
+
+        // An unrecorded build time is unknown, as is one without build information.
+        assert_eq!(BuildInfo(!0).build_time(), 0);
+        let mut footer = footer(1);
+        assert_eq!(image(&mut footer).build_time(), 0);
+    }
+
+    #[test]
+    fn test_copy_to() {
+        let mut source = Flash::<32>::new(b"mirror me", 3);
+        let mut bank = Flash::<56>([0xFF; 56]);
+        let base = bank.0.as_ptr() as u32;
+
+        let mut extension = Extension([!0; 2]);
+        extension.set_hw_rev(2);
+        extension.set_grace();
+        let mut image = Image::from_slice(&mut source.0);
+        image.extension = Some(&mut extension);
+        assert!(image.seal_footer());
+        image.footer.set_valid();
+        image.footer.decrement_attempts();
+        assert!(image.mark_booting());
+        image.mark_started();
+        assert!(image.request_swap());
+        assert!(image.take_swap_request());
+        assert!(image.take_grace());
+        {
+            let extension = image.extension.as_mut().unwrap();
+            extension.set_forgiven();
+            extension.set_writing();
+            extension.increment_write_count();
+            extension.decrement_extra_attempts();
+        }
+        let sealed = image.extension.as_ref().unwrap().footer_crc();
+        let mut writer = BufferWriter {
+            base,
+            buffer: &mut bank.0,
+        };
+        assert!(image.copy_to(&mut writer, base + 32));
+        assert!(!image.copy_to(&mut MockWriter::default(), 8));
+        assert_eq!(&bank.0[23..32], b"mirror me");
+        let pristine = bank.0;
+
+        // The copy's extension keeps only what describes the image, not its boot history.
+        let mut word = [0; 8];
+        word.copy_from_slice(&bank.0[40..48]);
+        let first = u64::from_ne_bytes(word);
+        word.copy_from_slice(&bank.0[48..56]);
+        let mut copied = Extension([first, u64::from_ne_bytes(word)]);
+        let mut expected = Extension([!0; 2]);
+        expected.set_hw_rev(2);
+        expected.set_grace();
+        expected.set_footer_crc(sealed.unwrap());
+        assert_eq!(copied.0, expected.0);
+
+        // The copy has to earn its own validity.
+        {
+            let mut copy = Image::from_slice(&mut bank.0[..40]);
+            copy.extension = Some(&mut copied);
+            assert!(copy.verify_self());
+            assert_eq!(copy.length(), 9);
+            assert!(!copy.footer().valid());
+            assert_eq!(copy.attempts(), Footer::MAX_ATTEMPTS);
+            assert!(copy.needs_checksum());
+            assert!(copy.verify_bootable());
+            assert_eq!(copy.cached_result(), Some(true));
+        }
+
+        // A torn or corrupted copy is caught.
+        let mut bank = Flash::<56>(pristine);
+        bank.0[27] ^= 0x01;
+        let mut copy = Image::from_slice(&mut bank.0[..40]);
+        assert!(!copy.verify_bootable());
+        assert!(copy.footer().invalid());
+    }
+
+    #[test]
+    fn test_encode_status() {
+        let pristine = footer(7);
+        assert_eq!(pristine.encode_status(), [7, 4, 0, Footer::STATUS_ENCODING]);
+
+        let mut trial = footer(8);
+        trial.set_valid();
+        trial.decrement_attempts();
+        assert_eq!(trial.encode_status(), [8, 3, 0b0001, 1]);
+
+        let mut confirmed = footer(9);
+        confirmed.set_valid();
+        confirmed.set_success();
+        let mut failed = footer(10);
+        failed.set_invalid();
+        failed.set_failure();
+        failed.decrement_attempts();
+        failed.decrement_attempts();
+
+        for footer in &[pristine, trial, confirmed, failed] {
+            let (version, attempts, flags) = Footer::decode_status(footer.encode_status()).unwrap();
+            assert_eq!(version, footer.version());
+            assert_eq!(attempts, footer.attempts());
+            assert_eq!(flags.valid, footer.valid());
+            assert_eq!(flags.invalid, footer.invalid());
+            assert_eq!(flags.success, footer.success());
+            assert_eq!(flags.failure, footer.failure());
+        }
+        let (_, _, flags) = Footer::decode_status([10, 2, 0b1010, 1]).unwrap();
+        assert_eq!(
+            flags,
+            StatusFlags {
+                invalid: true,
+                failure: true,
+                ..StatusFlags::default()
+            }
+        );
+        assert_eq!(Footer::decode_status([1, 4, 0, 2]), None);
+    }
+
+    #[test]
+    fn test_select_with_bank() {
+        let mut footer_a = verified(2);
+        let mut footer_b = verified(2);
+        let mut footer_c = verified(1);
+        let mut images = [
+            image(&mut footer_a),
+            image(&mut footer_b),
+            image(&mut footer_c),
+        ];
+        images[1].index = 1;
+        images[2].index = 1;
+        let bank_of = |image: &Image| image.index();
+
+        // The tie at version 2 goes to whichever bank is active.
+        let selected = select_with_bank(&mut images, 0, bank_of);
+        assert_eq!(selected.map(|image| image.index()), Some(0));
+        let selected = select_with_bank(&mut images, 1, bank_of);
+        assert_eq!(selected.map(|image| image.index()), Some(1));
+
+        // A newer image in the inactive bank still wins.
+        images[1].footer.0 = verified(3).0;
+        let selected = select_with_bank(&mut images, 0, bank_of);
+        assert_eq!(selected.map(|image| image.footer().version()), Some(3));
+    }
+
+    #[test]
+    fn test_is_exact_duplicate_of() {
+        let mut original = Footer(footer(1).0 | 0x12_3456);
+        let mut duplicate = Footer(footer(1).0 | 0x12_3456);
+        duplicate.set_valid();
+        duplicate.decrement_attempts();
+        let mut rebuilt = Footer(footer(1).0 | 0x65_4321);
+        let mut longer = Footer(original.0 & !(0xFF_FFFF << 32) | 5 << 32);
+        let mut newer = Footer(footer(2).0 | 0x12_3456);
+
+        let original = image(&mut original);
+        assert!(original.is_exact_duplicate_of(&image(&mut duplicate)));
+        assert!(original.is_exact_duplicate_of(&original));
+
+        let rebuilt = image(&mut rebuilt);
+        assert!(original == rebuilt);
+        assert!(!original.is_exact_duplicate_of(&rebuilt));
+        assert!(!original.is_exact_duplicate_of(&image(&mut longer)));
+        assert!(!original.is_exact_duplicate_of(&image(&mut newer)));
+    }
+
+    #[test]
+    fn test_select_oldest() {
+        let mut footer_a = footer(3);
+        footer_a.set_valid();
+        let mut footer_b = footer(1);
+        footer_b.set_failure();
+        let mut footer_c = footer(2);
+        footer_c.set_valid();
+        let mut footer_d = footer(4);
+        footer_d.set_valid();
+        let mut images = [
+            image(&mut footer_a),
+            image(&mut footer_b),
+            image(&mut footer_c),
+            image(&mut footer_d),
+        ];
+
+        let selected = select_oldest(&mut images);
+        assert_eq!(selected.map(|image| image.footer().version()), Some(2));
+        assert!(images.iter().all(|image| !image.footer().invalid()));
+
+        let mut footer = footer(1);
+        footer.set_invalid();
+        assert!(select_oldest(&mut [image(&mut footer)]).is_none());
+        assert!(select_oldest(&mut [] as &mut [Image]).is_none());
+    }
+
+    #[test]
+    fn test_cached_result() {
+        let mut footer = footer(1);
+        let blank = footer.0;
+        let mut image = image(&mut footer);
+        assert_eq!(image.cached_result(), None);
+
+        let mut engine = MockCrcEngine::returning(0);
+        assert!(image.verify_bootable_using(&mut engine));
+        assert_eq!(image.cached_result(), Some(true));
+        assert_eq!(engine.runs(), 1);
+
+        // Simulate the valid bit never having made it to flash.
+        image.footer.0 = blank;
+        assert!(image.verify_bootable_using(&mut engine));
+        assert!(image.is_bootable());
+        assert_eq!(engine.runs(), 1);
+    }
+
+    #[test]
+    fn test_cached_result_invalid() {
+        let mut footer = footer(1);
+        let blank = footer.0;
+        let mut image = image(&mut footer);
+
+        let mut engine = MockCrcEngine::returning(0x12_3456);
+        assert!(!image.verify_bootable_using(&mut engine));
+        assert_eq!(image.cached_result(), Some(false));
+
+        image.footer.0 = blank;
+        assert!(!image.verify_bootable_using(&mut engine));
+        assert!(!image.is_bootable());
+        assert_eq!(engine.runs(), 1);
+    }
+
+    #[test]
+    fn test_verify_bootable_reporting() {
+        let mut flash = Flash::<16>::new(b"ABCD", 1);
+        flash.0[4] ^= 0x01;
+        let computed = crc::calculate(&flash.0[4..8]);
+        let stored = crc::calculate(b"ABCD");
+        let mut image = Image::from_slice(&mut flash.0);
+
+        let mut reported = None;
+        assert!(!image
+            .verify_bootable_reporting(|computed, stored| { reported = Some((computed, stored)) }));
+        assert_eq!(reported, Some((computed, stored)));
+        assert!(image.footer().invalid());
+
+        let mut reported = false;
+        assert!(!image.verify_bootable_reporting(|_, _| reported = true));
+        assert!(!reported);
+    }
+
+    #[test]
+    fn test_verify_bootable_reporting_valid() {
+        let mut flash = Flash::<16>::new(b"ABCD", 1);
+        let mut image = Image::from_slice(&mut flash.0);
+
+        let mut reported = false;
+        assert!(image.verify_bootable_reporting(|_, _| reported = true));
+        assert!(!reported);
+        assert!(image.footer().valid());
+    }
+
+    #[test]
+    fn test_reset_status() {
+        let pristine = footer(2).0 | 0x11_1111;
+        let mut footer = Footer(pristine);
+        footer.set_valid();
+        footer.set_failure();
+        footer.set_n_attempts(0);
+        assert_eq!(footer.attempts(), 0);
+
+        footer.reset_status();
+        assert_eq!(footer.bits(), pristine);
+        assert_eq!(footer.describe().checksum, 0x11_1111);
+        assert_eq!(footer.describe().version, 2);
+        assert_eq!(footer.describe().length, 4);
+        assert!(!footer.valid() && !footer.invalid() && !footer.success() && !footer.failure());
+        assert_eq!(footer.attempts(), Footer::MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_copy_status_from() {
+        let mut confirmed = Footer(footer(2).0 | 0x11_1111);
+        confirmed.set_valid();
+        confirmed.decrement_attempts();
+        confirmed.set_success();
+
+        let mut duplicate = Footer(footer(1).0 | 0x22_2222);
+        duplicate.copy_status_from(&confirmed);
+
+        let expected = FooterInfo {
+            checksum: 0x22_2222,
+            version: 1,
+            length: 4,
+            ..confirmed.describe()
+        };
+        assert_eq!(duplicate.describe(), expected);
+        assert_eq!(duplicate.attempts(), Footer::MAX_ATTEMPTS - 1);
+    }
+
+    #[test]
+    fn test_begin_write() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        let mut image = extended(&mut footer, &mut extension);
+        let mut writer = MockWriter::default();
+
+        assert!(image.begin_write(&mut writer));
+        assert!(image.is_writing());
+        assert_eq!(writer.writes, 2);
+        assert_eq!(image.footer_write_count(), 1);
+        assert!(!image.is_bootable());
+        assert!(!image.verify_bootable());
+        assert!(!image.footer().invalid());
+
+        image.finish_write(&mut writer);
+        assert!(!image.is_writing());
+        assert_eq!(image.footer_write_count(), 2);
+        assert!(image.verify_bootable());
+    }
+
+    #[test]
+    fn test_begin_write_no_extension() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut image = image(&mut footer);
+        let mut writer = MockWriter::default();
+
+        assert!(!image.begin_write(&mut writer));
+        assert_eq!(writer.writes, 0);
+        assert!(!image.is_writing());
+        assert!(image.verify_bootable());
+    }
+
+    #[test]
+    fn test_seal_footer() {
+        let mut footer = Footer(footer(1).0 | 0xAB_CDEF);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        let mut image = extended(&mut footer, &mut extension);
+
+        assert!(image.footer_crc_valid());
+        assert!(image.seal_footer());
+        assert!(image.footer_crc_valid());
+        assert!(image.is_bootable());
+
+        // The status bits aren't covered by the CRC, nor are the extension's markers.
+        image.footer.decrement_attempts();
+        assert!(image.mark_booting());
+        image.mark_started();
+        assert!(image.verify_bootable());
+
+        image.footer.0 ^= 1 << 40;
+        assert!(image.footer().sane());
+        assert!(!image.is_bootable());
+        assert!(!image.verify_bootable());
+        assert!(image.footer().invalid());
+    }
+
+    #[test]
+    fn test_seal_footer_extension() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        extension.set_hw_rev(3);
+        let mut image = extended(&mut footer, &mut extension);
+        assert!(image.seal_footer());
+        assert!(image.verify_self());
+
+        // Every field recorded when the image is flashed is covered by the CRC.
+        let bits = (4..32)
+            .map(|bit| (0, bit))
+            .chain((72..80).map(|bit| (1, bit - 64)));
+        for (word, bit) in bits {
+            let extension = image.extension.as_mut().unwrap();
+            extension.0[word] ^= 1 << bit;
+            assert!(!image.footer_crc_valid(), "bit {}", word * 64 + bit);
+            let extension = image.extension.as_mut().unwrap();
+            extension.0[word] ^= 1 << bit;
+        }
+        assert!(image.verify_self());
+    }
+
+    #[test]
+    fn test_seal_footer_no_extension() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut image = image(&mut footer);
+
+        assert!(!image.seal_footer());
+        image.footer.0 ^= 1 << 40;
+        assert!(image.verify_bootable());
+    }
+
+    #[test]
+    fn test_metadata_crc() {
+        let footer = Footer(0x3A00_1234_0598_7654);
+        assert_eq!(
+            Footer(0xFA00_1234_0598_7654).metadata_crc(),
+            footer.metadata_crc()
+        );
+        for bit in 0..56 {
+            let corrupt = Footer(footer.0 ^ 1 << bit);
+            assert_ne!(corrupt.metadata_crc(), footer.metadata_crc(), "bit {}", bit);
+        }
+    }
+
+    #[test]
+    fn test_from_slice() {
+        let mut flash = Flash::<24>::new(b"ABCD", 1);
+        let image = Image::from_slice(&mut flash.0);
+        assert_eq!(image.footer().checksum(), 0xA7629E);
+        assert_eq!(image.footer().length(), 4);
+        assert_eq!(image.contents(), Some(&b"ABCD"[..]));
+    }
+
+    #[test]
+    #[should_panic(expected = "image extends beyond the start of the buffer")]
+    fn test_from_slice_too_long() {
+        let mut flash = Flash::<16>::new(b"ABCD", 1);
+        flash.0[12] = 9;
+        Image::from_slice(&mut flash.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "footer is misaligned")]
+    fn test_from_slice_misaligned() {
+        let mut flash = Flash::<24>::new(b"ABCD", 1);
+        Image::from_slice(&mut flash.0[..23]);
+    }
+
+    #[test]
+    fn test_try_from() {
+        let mut short = Flash::<8>([0xFF; 8]);
+        assert_eq!(
+            Image::try_from(&mut short.0[..7]).err(),
+            Some(SliceError::TooShort)
+        );
+        assert!(Image::try_from(&mut short.0[..]).is_ok());
+
+        let mut flash = Flash::<24>::new(b"ABCD", 1);
+        assert_eq!(
+            Image::try_from(&mut flash.0[..23]).err(),
+            Some(SliceError::Misaligned)
+        );
+        assert_eq!(
+            Image::try_from(&mut flash.0[8..]).map(|image| image.footer().length()),
+            Ok(4)
+        );
+        assert_eq!(
+            Image::try_from(&mut flash.0[13..]).err(),
+            Some(SliceError::TooLong)
+        );
+
+        let mut image = Image::try_from(&mut flash.0[..]).unwrap();
+        assert!(image.verify_bootable());
+    }
+
+    #[test]
+    fn test_from_slice_verify_bootable() {
+        let mut flash = Flash::<32>::new(b"0123456789", 1);
+        assert!(Image::from_slice(&mut flash.0).verify_bootable());
+        assert!(Image::from_slice(&mut flash.0).footer().valid());
+
+        let mut corrupt = Flash::<32>::new(b"0123456789", 2);
+        corrupt.0[20] ^= 0x01;
+        let mut image = Image::from_slice(&mut corrupt.0);
+        assert!(!image.verify_bootable());
+        assert!(image.footer().invalid());
+
+        let mut flash_b = Flash::<32>::new(b"9876543210", 3);
+        let mut images = [
+            Image::from_slice(&mut flash.0),
+            Image::from_slice(&mut flash_b.0),
+            Image::from_slice(&mut corrupt.0),
+        ];
+        let selected = select_by(&mut images, Image::cmp);
+        assert_eq!(selected.map(|image| image.footer().version()), Some(3));
+    }
+
+    /// Returns an image whose start address precedes the beginning of the address space.
+    fn with_underflow<R>(f: impl FnOnce(&mut Image) -> R) -> R {
+        let mut footer = Footer(footer(1).0 | 0xFF_FFFF << 32);
+        let mut extension = Extension([!0; 2]);
+        extension.set_length_hi(0xFF);
+        let mut image = extended(&mut footer, &mut extension);
+        assert_eq!(image.start_address(), None);
+        f(&mut image)
+    }
+
+    #[test]
+    #[cfg(not(feature = "fatal-hook"))]
+    #[should_panic(expected = "image starts below address zero")]
+    fn test_fatal() {
+        with_underflow(|image| image.prepare_boot());
+    }
+
+    #[test]
+    #[cfg(not(feature = "fatal-hook"))]
+    #[should_panic(expected = "scan region address is null")]
+    fn test_footer_scanner_null() {
+        unsafe { FooterScanner::new(0, 64, 8) };
+    }
+
+    #[cfg(feature = "fatal-hook")]
+    thread_local! {
+        static FATAL_CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[cfg(feature = "fatal-hook")]
+    #[no_mangle]
+    pub fn switcher_on_fatal() -> ! {
+        FATAL_CALLS.with(|calls| calls.set(calls.get() + 1));
+        panic!("on_fatal")
+    }
+
+    #[test]
+    #[cfg(feature = "fatal-hook")]
+    fn test_fatal_hook() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let calls = FATAL_CALLS.with(Cell::get);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            with_underflow(|image| image.prepare_optimistic_boot())
+        }));
+        assert!(result.is_err());
+        assert_eq!(FATAL_CALLS.with(Cell::get), calls + 1);
+    }
+
+    #[test]
+    fn test_chunked_checksum() {
+        let mut contents = [0; 3 * VERIFY_CHUNK_BYTES];
+        for (i, byte) in contents.iter_mut().enumerate() {
+            *byte = (i * 7 + i / 13) as u8;
+        }
+
+        for &length in &[
+            1,
+            3,
+            VERIFY_CHUNK_BYTES - 1,
+            VERIFY_CHUNK_BYTES,
+            2 * VERIFY_CHUNK_BYTES + 5,
+        ] {
+            let mut flash = Flash::<{ 3 * VERIFY_CHUNK_BYTES + 8 }>::new(&contents[..length], 1);
+            let image = Image::from_slice(&mut flash.0);
+            assert_eq!(
+                image.calculate_checksum(),
+                Some(crc::calculate(&contents[..length])),
+                "{}",
+                length
+            );
+        }
+
+        let pristine = Flash::<{ 2 * VERIFY_CHUNK_BYTES }>::new(&contents[..300], 1);
+        let mut flash = Flash(pristine.0);
+        assert!(Image::from_slice(&mut flash.0).verify_bootable());
+        let mut flash = Flash(pristine.0);
+        flash.0[VERIFY_CHUNK_BYTES] ^= 0x80;
+        assert!(!Image::from_slice(&mut flash.0).verify_bootable());
+    }
+
+    #[test]
+    fn test_reset_vector() {
+        let mut contents = [0; 12];
+        contents[..4].copy_from_slice(&0x2000_8000u32.to_ne_bytes());
+        contents[4..8].copy_from_slice(&0x0800_0141u32.to_ne_bytes());
+        let mut flash = Flash::<24>::new(&contents, 1);
+        assert_eq!(
+            Image::from_slice(&mut flash.0).reset_vector(),
+            Some(0x0800_0141)
+        );
+
+        let mut flash = Flash::<16>::new(&[0; 7], 1);
+        assert_eq!(Image::from_slice(&mut flash.0).reset_vector(), None);
+    }
+
+    #[test]
+    fn test_reset_vector_plausible() {
+        let mut flash = Flash::<32>::new(&[0; 16], 1);
+        let start = &flash.0[8] as *const u8 as u32;
+        let footer = start + 16;
+        let with_vector = |flash: &mut Flash<32>, vector: u32| {
+            flash.0[12..16].copy_from_slice(&vector.to_ne_bytes());
+            Image::from_slice(&mut flash.0).reset_vector_plausible()
+        };
+
+        assert!(with_vector(&mut flash, start | 1));
+        assert!(with_vector(&mut flash, (start + 9) | 1));
+        assert!(with_vector(&mut flash, (footer - 2) | 1));
+        // Not a Thumb address.
+        assert!(!with_vector(&mut flash, start + 8));
+        // Outside of the image.
+        assert!(!with_vector(&mut flash, 0));
+        assert!(!with_vector(&mut flash, footer | 1));
+        assert!(!with_vector(&mut flash, (start - 2) | 1));
+        assert!(!with_vector(&mut flash, !0));
+
+        // The image is too short to hold a reset vector.
+        let mut flash = Flash::<16>::new(&[0xFF; 4], 1);
+        assert!(!Image::from_slice(&mut flash.0).reset_vector_plausible());
+    }
+
+    #[test]
+    fn test_estimated_crc_cycles() {
+        let cycles = |length: u64| {
+            let mut footer = Footer(0xFF << 56 | length << 32);
+            image(&mut footer).estimated_crc_cycles()
+        };
+
+        let per_byte = crc::CYCLES_PER_BYTE;
+        let zeros = crc::CRC_WIDTH_BYTES as u32 * per_byte;
+        assert_eq!(cycles(0), zeros);
+        assert_eq!(cycles(1), zeros + per_byte);
+        assert_eq!(cycles(1000) - cycles(0), 1000 * per_byte);
+        assert_eq!(cycles(2000) - cycles(1000), cycles(1000) - cycles(0));
+        assert_eq!(cycles(0xFF_FFFF), (0xFF_FFFF + 3) * per_byte);
+    }
+
+    #[test]
+    fn test_select_preferring_version() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut footer_c = footer(3);
+        footer_c.set_valid();
+        let mut footer_d = footer(4);
+        footer_d.set_invalid();
+        let mut images = [
+            image(&mut footer_a),
+            image(&mut footer_b),
+            image(&mut footer_c),
+            image(&mut footer_d),
+        ];
+
+        let version = |image: Option<&mut Image>| image.map(|image| image.footer().version());
+        assert_eq!(version(select_preferring_version(&mut images, 2)), Some(2));
+        assert_eq!(version(select_preferring_version(&mut images, 1)), Some(1));
+        assert_eq!(version(select_preferring_version(&mut images, 4)), Some(3));
+        assert_eq!(version(select_preferring_version(&mut images, 9)), Some(3));
+        assert!(select_preferring_version(&mut [] as &mut [Image], 1).is_none());
+    }
+
+    #[test]
+    fn test_bits() {
+        let footer = Footer(0x3A00_1234_0598_7654);
+        assert_eq!(footer.bits(), 0x3A00_1234_0598_7654);
+        assert_eq!(footer.length(), 0x1234);
+
+        let footer = Footer(0xFF00_0000_0000_0000 | 0xAB_CDEF << 32);
+        assert_eq!(footer.length(), 0xAB_CDEF);
+        assert_eq!(footer.bits() >> 32 & 0xFF_FFFF, 0xAB_CDEF);
+        assert_eq!(footer.checksum(), 0);
+        assert_eq!(footer.version(), 0);
+
+        let mut footer = footer;
+        assert_eq!(footer.attempts(), Footer::MAX_ATTEMPTS);
+        footer.decrement_attempts();
+        footer.decrement_attempts();
+        footer.decrement_attempts();
+        assert_eq!(footer.attempts(), 1);
+        assert_eq!(footer.bits() >> 60, 0b1000);
+        footer.decrement_attempts();
+        assert_eq!(footer.bits() >> 63, 0);
+        assert_eq!(footer.length(), 0xAB_CDEF);
+        footer.reset_attempts();
+        assert_eq!(footer.bits(), 0xFF00_0000_0000_0000 | 0xAB_CDEF << 32);
+    }
+
+    #[test]
+    fn test_select_with_fallback() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        footer_a.set_n_attempts(0);
+        let mut footer_b = footer(3);
+        footer_b.set_invalid();
+        footer_b.set_n_attempts(0);
+        let mut footer_c = footer(2);
+        footer_c.set_valid();
+        footer_c.set_failure();
+        let mut images = [
+            image(&mut footer_a),
+            image(&mut footer_b),
+            image(&mut footer_c),
+        ];
+        assert!(select_by(&mut images, Image::cmp).is_none());
+
+        let selected = select_with_fallback(&mut images);
+        assert_eq!(selected.map(|image| image.footer().version()), Some(2));
+        assert!(!images[2].footer().invalid());
+    }
+
+    #[test]
+    fn test_select_with_fallback_bootable() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        footer_b.set_n_attempts(0);
+        let mut images = [image(&mut footer_a), image(&mut footer_b)];
+
+        let selected = select_with_fallback(&mut images);
+        assert_eq!(selected.map(|image| image.footer().version()), Some(1));
+
+        let mut footer = Footer(!0);
+        assert!(select_with_fallback(&mut [image(&mut footer)]).is_none());
+        assert!(select_with_fallback(&mut [] as &mut [Image]).is_none());
+    }
+
+    #[test]
+    fn test_mark_booting() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        let mut image = extended(&mut footer, &mut extension);
+        assert!(!image.was_booting());
+
+        assert!(image.mark_booting());
+        assert!(image.was_booting());
+        image.mark_success();
+        assert!(!image.was_booting());
+
+        // Having started, the image can't be marked again until the extension is erased.
+        assert!(!image.mark_booting());
+        assert!(!image.was_booting());
+
+        let mut footer = self::footer(1);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        let mut image = extended(&mut footer, &mut extension);
+        assert!(image.mark_booting());
+        assert!(image.mark_booting());
+        image.mark_failure();
+        assert!(!image.was_booting());
+    }
+
+    #[test]
+    fn test_mark_booting_reset() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        assert!(extended(&mut footer, &mut extension).mark_booting());
+
+        // The next boot finds the marker left by the previous one.
+        let next = extended(&mut footer, &mut extension);
+        assert!(next.was_booting());
+        assert!(next.is_bootable());
+
+        let mut footer = self::footer(1);
+        let mut image = image(&mut footer);
+        assert!(!image.mark_booting());
+        assert!(!image.was_booting());
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut image = image(&mut footer).with_max_attempts::<2>();
+        assert_eq!(image.attempts(), 2);
+
+        image.prepare_boot();
+        assert_eq!(image.attempts(), 1);
+        assert!(image.verify_bootable());
+        image.prepare_boot();
+        assert_eq!(image.attempts(), 0);
+        assert!(!image.verify_bootable());
+        image.prepare_boot();
+        assert_eq!(image.attempts(), 0);
+
+        image.mark_success();
+        assert_eq!(image.attempts(), 2);
+
+        // An extension's attempts are surplus to a small budget and are never consumed.
+        let mut footer = self::footer(1);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        let mut image = extended(&mut footer, &mut extension).with_max_attempts::<2>();
+        assert_eq!(image.attempts(), 2);
+        for _ in 0..4 {
+            image.prepare_boot();
+        }
+        assert_eq!(image.attempts(), 0);
+        assert!(!image.verify_bootable());
+        assert_eq!(extension.extra_attempts(), Extension::EXTRA_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_max_attempts_eight() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        let mut trial = extended(&mut footer, &mut extension).with_max_attempts::<8>();
+        assert_eq!(Image::<8>::RECORDABLE_ATTEMPTS, 8);
+        assert_eq!(trial.attempts(), 8);
+
+        for attempts in (0..8).rev() {
+            assert!(trial.verify_bootable());
+            trial.prepare_boot();
+            assert_eq!(trial.attempts(), attempts);
+        }
+        assert_eq!(trial.footer().attempts(), 0);
+        assert!(!trial.verify_bootable());
+        assert_eq!(trial.consume_attempt(), 0);
+
+        trial.mark_success();
+        assert_eq!(trial.attempts(), 8);
+        assert_eq!(footer.attempts(), 0);
+        assert_eq!(extension.extra_attempts(), 0);
+
+        // An image which has been through a trial boot only ever clears bits.
+        let mut footer = self::footer(1);
+        footer.set_valid();
+        let mut extension = Extension([!0; 2]);
+        let erased = Extension(extension.0);
+        let mut trial = extended(&mut footer, &mut extension).with_max_attempts::<8>();
+        for _ in 0..6 {
+            trial.consume_attempt();
+        }
+        assert_eq!(trial.attempts(), 2);
+        assert!(extension.only_clears(&erased));
+        assert_eq!(extension.extra_attempts(), 2);
+
+        // Without an extension, only the footer's attempts can be recorded.
+        let mut footer = self::footer(1);
+        footer.set_valid();
+        let mut plain = image(&mut footer).with_max_attempts::<8>();
+        assert_eq!(plain.attempts(), Footer::MAX_ATTEMPTS);
+        plain.prepare_boot();
+        assert_eq!(plain.attempts(), Footer::MAX_ATTEMPTS - 1);
+    }
+
+    #[test]
+    fn test_max_attempts_select() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        footer_b.decrement_attempts();
+        let mut images = [
+            image(&mut footer_a).with_max_attempts::<1>(),
+            image(&mut footer_b).with_max_attempts::<1>(),
+        ];
+        assert_eq!(images[1].attempts(), 0);
+
+        let selected = select_by(&mut images, Image::cmp);
+        assert_eq!(selected.map(|image| image.footer().version()), Some(1));
+
+        let mut footer = self::footer(1);
+        let image = image(&mut footer).with_max_attempts::<{ Footer::MAX_ATTEMPTS as u8 }>();
+        assert_eq!(image.attempts(), Footer::MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_index() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        assert_eq!(image(&mut footer_a).index(), 0);
+
+        // Image::from_indexed() can't be used on the host, since the address of the footer can't
+        // be round-tripped through a u32.
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut images = [
+            Image {
+                index: 7,
+                ..image(&mut footer_a)
+            },
+            Image {
+                index: 3,
+                ..image(&mut footer_b)
+            },
+        ];
+        let selected = select_by(&mut images, Image::cmp).unwrap();
+        assert_eq!(selected.index(), 3);
+        assert!(format!("{:?}", selected).starts_with("Image { index: 3, "));
+
+        let [a, _] = images;
+        assert_eq!(a.with_max_attempts::<2>().index(), 7);
+    }
+
+    #[test]
+    fn test_evaluate_footer() {
+        let contents = b"0123456789";
+        let bits = Flash::<24>::new(contents, 1).footer().bits();
+        let with = |status: u64| bits & !(0xFF << 56) | status << 56;
+
+        assert_eq!(evaluate_footer(!0, &[]), BootStatus::Erased);
+        assert_eq!(evaluate_footer(bits, contents), BootStatus::Trial);
+        assert_eq!(evaluate_footer(bits, b"--0123456789"), BootStatus::Trial);
+        assert_eq!(evaluate_footer(bits, &contents[1..]), BootStatus::Unsound);
+        assert_eq!(
+            evaluate_footer(bits & !(0xFF_FFFF << 32), &[]),
+            BootStatus::Unsound
+        );
+        assert_eq!(
+            evaluate_footer(bits, b"0123456788"),
+            BootStatus::ChecksumInvalid
+        );
+        assert_eq!(
+            evaluate_footer(with(0b1111_1110), b"0123456788"),
+            BootStatus::Trial
+        );
+        assert_eq!(
+            evaluate_footer(with(0b1111_1101), contents),
+            BootStatus::Invalid
+        );
+        assert_eq!(evaluate_footer(with(0b1111_1011), b""), BootStatus::Unsound);
+        assert_eq!(
+            evaluate_footer(with(0b1111_1011), contents),
+            BootStatus::Confirmed
+        );
+        assert_eq!(
+            evaluate_footer(with(0b1111_0111), contents),
+            BootStatus::Failed
+        );
+        assert_eq!(
+            evaluate_footer(with(0b1111_0011), contents),
+            BootStatus::Unsound
+        );
+        assert_eq!(
+            evaluate_footer(with(0b0000_1111), contents),
+            BootStatus::Exhausted
+        );
+        assert!(!BootStatus::Exhausted.is_bootable());
+    }
+
+    #[test]
+    fn test_evaluate_footer_agrees() {
+        // Every combination of the status bits, with both a good and a bad checksum.
+        for status in 0..=0xFF {
+            for &checksum in &[0, 0x5A_5A5A] {
+                let mut flash = Flash::<24>::new(b"0123456789", 1);
+                let bits = flash.footer().bits() & !(0xFF << 56) ^ checksum | status << 56;
+                flash.0[16..].copy_from_slice(&bits.to_le_bytes());
+
+                let evaluated = evaluate_footer(bits, &flash.0[..16]);
+                let mut image = Image::from_slice(&mut flash.0);
+                assert_eq!(evaluated.is_bootable(), image.is_bootable(), "{:#x}", bits);
+                assert_eq!(
+                    evaluated.is_bootable(),
+                    image.verify_bootable(),
+                    "{:#x}",
+                    bits
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_select_for_hardware() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut footer_c = footer(3);
+        footer_c.set_valid();
+        let mut extension_a = Extension([!0; 2]);
+        let mut extension_b = Extension([!0; 2]);
+        extension_b.set_hw_rev(2);
+        let mut extension_c = Extension([!0; 2]);
+        extension_c.set_hw_rev(3);
+        let mut images = [
+            extended(&mut footer_a, &mut extension_a),
+            extended(&mut footer_b, &mut extension_b),
+            extended(&mut footer_c, &mut extension_c),
+        ];
+        assert!(images[0].supports_hardware(2));
+        assert!(!images[2].supports_hardware(2));
+
+        let version = |image: Option<&mut Image>| image.map(|image| image.footer().version());
+        assert_eq!(version(select_for_hardware(&mut images, 2)), Some(2));
+        assert_eq!(version(select_for_hardware(&mut images, 3)), Some(3));
+        assert_eq!(version(select_for_hardware(&mut images, 1)), Some(1));
+        assert!(!images[2].footer().invalid());
+    }
+
+    #[test]
+    fn test_footer_scanner() {
+        #[repr(C, align(8))]
+        struct Region([u8; 256]);
+
+        // Fill the region with garbage, then embed a single image in it.
+        let mut region = Region([0; 256]);
+        let mut state = 0x1234_5678u32;
+        for byte in region.0.iter_mut() {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            *byte = (state >> 16) as u8;
+        }
+        region.0[192..200].copy_from_slice(&[0xFF; 8]);
+        let image = Flash::<32>::new(b"recovery", 7);
+        region.0[96..128].copy_from_slice(&image.0);
+        let footer_address = &region.0[120] as *const u8 as usize;
+
+        for &step in &[4, 8, 24] {
+            let mut scanner = FooterScanner::from_slice(&mut region.0, step);
+            let found = scanner.next().expect("image not found");
+            assert_eq!(found.footer().version(), 7);
+            assert_eq!(found.footer().bits(), image.footer().bits());
+            assert_eq!(found.footer as *const Footer as usize, footer_address);
+            assert!(scanner.next().is_none());
+        }
+
+        // A step which never lands on the footer finds nothing.
+        assert_eq!(FooterScanner::from_slice(&mut region.0, 16).count(), 0);
+        assert_eq!(
+            FooterScanner::from_slice(&mut region.0[..120], 8).count(),
+            0
+        );
+        assert_eq!(FooterScanner::from_slice(&mut [], 8).count(), 0);
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic(expected = "more than MAX_IMAGES images")
+    )]
+    fn test_select_too_many() {
+        let mut footers: std::vec::Vec<Footer> = (0..=MAX_IMAGES as u8).map(footer).collect();
+        let mut images: std::vec::Vec<Image> = footers.iter_mut().map(image).collect();
+
+        // The newest image is past the limit, so it is never considered.
+        let selected = select_by(&mut images, Image::cmp);
+        assert_eq!(
+            selected.map(|image| image.footer().version()),
+            Some(MAX_IMAGES as u8 - 1)
+        );
+        assert!(!images[MAX_IMAGES].footer().valid());
+    }
+
+    #[test]
+    fn test_confirm_update() {
+        for &(fallback, attempts, failed) in &[
+            (Fallback::Keep, 3, false),
+            (Fallback::Attempts(1), 1, false),
+            (Fallback::Attempts(4), 3, false),
+            (Fallback::Retire, 3, true),
+        ] {
+            let mut footer_new = footer(2);
+            let mut footer_old = Footer(footer(1).0 & !(1 << 60));
+            let mut new = image(&mut footer_new);
+            let mut old = image(&mut footer_old);
+            new.footer.decrement_attempts();
+
+            confirm_update(&mut new, &mut old, fallback);
+            assert!(new.is_confirmed(), "{:?}", fallback);
+            assert_eq!(new.attempts(), 4, "{:?}", fallback);
+            assert_eq!(old.attempts(), attempts, "{:?}", fallback);
+            assert_eq!(old.is_failed(), failed, "{:?}", fallback);
+            assert!(!old.is_confirmed(), "{:?}", fallback);
+        }
+    }
+
+    #[test]
+    fn test_select_verbose() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut flash_b = Flash::<16>::new(b"ABCD", 2);
+        flash_b.0[4] ^= 0x01;
+        let mut footer_c = Footer(footer(3).0 & !(1 << 57));
+        let mut footer_d = Footer(footer(4).0 & !(1 << 59));
+        let mut footer_e = Footer(!0);
+        let mut footer_f = Footer(footer(5).0 & !(0xF << 60));
+        footer_f.set_valid();
+
+        let mut images = [
+            image(&mut footer_a),
+            Image::from_slice(&mut flash_b.0),
+            image(&mut footer_c),
+            image(&mut footer_d),
+            image(&mut footer_e),
+            image(&mut footer_f),
+        ];
+        let (selected, statuses) = select_verbose(&mut images);
+
+        assert_eq!(selected.map(|image| image.footer().version()), Some(1));
+        assert_eq!(
+            statuses,
+            [
+                BootStatus::Trial,
+                BootStatus::ChecksumInvalid,
+                BootStatus::Invalid,
+                BootStatus::Failed,
+                BootStatus::Erased,
+                BootStatus::Exhausted,
+            ]
+        );
+        assert!(images[1].footer().invalid());
+
+        let (selected, statuses) = select_verbose::<0, 4>(&mut []);
+        assert!(selected.is_none());
+        assert_eq!(statuses, []);
+    }
+
+    #[test]
+    fn test_select_compatible() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut extension_a = Extension([!0; 2]);
+        extension_a.set_min_bootloader(3);
+        let mut extension_b = Extension([!0; 2]);
+        extension_b.set_min_bootloader(5);
+        let mut images = [
+            extended(&mut footer_a, &mut extension_a),
+            extended(&mut footer_b, &mut extension_b),
+        ];
+        assert!(images[0].supports_bootloader(3));
+        assert!(!images[1].supports_bootloader(4));
+
+        let version = |image: Option<&mut Image>| image.map(|image| image.footer().version());
+        assert_eq!(version(select_compatible(&mut images, 5)), Some(2));
+        assert_eq!(version(select_compatible(&mut images, 4)), Some(1));
+        assert_eq!(version(select_compatible(&mut images, 2)), None);
+        assert!(!images[1].footer().invalid());
+
+        let mut footer = footer(1);
+        footer.set_valid();
+        assert!(image(&mut footer).supports_bootloader(0));
+        assert_eq!(Extension([!0; 2]).min_bootloader(), 0);
+    }
+
+    #[test]
+    fn test_select_for_hardware_no_extension() {
+        let mut footer = footer(1);
+        footer.set_valid();
+        let mut images = [image(&mut footer)];
+        assert!(select_for_hardware(&mut images, 7).is_some());
+    }
+
+    #[test]
+    fn test_finish_verify() {
+        let contents = b"0123456789";
+        let mut flash = Flash::<24>::new(contents, 1);
+        let mut image = Image::from_slice(&mut flash.0);
+        assert!(image.needs_checksum());
+
+        let mut crc = Crc::new();
+        for chunk in contents.chunks(3) {
+            crc.update(chunk);
+        }
+        assert!(image.finish_verify(&crc));
+        assert!(image.footer().valid());
+        assert!(!image.needs_checksum());
+
+        // Once valid, the CRC is no longer consulted.
+        assert!(image.finish_verify(&Crc::new()));
+    }
+
+    #[test]
+    fn test_verify_bootable_cancellable() {
+        let mut flash = Flash::<24>::new(b"0123456789", 1);
+        let mut image = Image::from_slice(&mut flash.0);
+
+        // Cancel after the first chunk.
+        let mut polls = 0;
+        let result = image.verify_bootable_cancellable(4, || {
+            polls += 1;
+            polls == 1
+        });
+        assert_eq!(result, None);
+        assert_eq!(polls, 2);
+        assert!(!image.footer().valid());
+        assert!(!image.footer().invalid());
+        assert!(image.needs_checksum());
+
+        assert_eq!(image.verify_bootable_cancellable(4, || true), Some(true));
+        assert!(image.footer().valid());
+
+        // Nothing is left to compute, so the caller isn't polled.
+        assert_eq!(image.verify_bootable_cancellable(4, || false), Some(true));
+    }
+
+    #[test]
+    fn test_verify_bootable_cancellable_invalid() {
+        let mut flash = Flash::<24>::new(b"0123456789", 1);
+        flash.0[9] ^= 1;
+        let mut image = Image::from_slice(&mut flash.0);
+        assert_eq!(image.verify_bootable_cancellable(3, || true), Some(false));
+        assert!(image.footer().invalid());
+    }
+
+    #[test]
+    fn test_verify_bootable_with() {
+        let contents = b"0123456789";
+        let mut footer = Flash::<24>::new(contents, 1).footer();
+        assert!(image(&mut footer).verify_bootable_with(contents));
+        assert!(footer.valid());
+
+        let mut footer = Flash::<24>::new(contents, 1).footer();
+        assert!(!image(&mut footer).verify_bootable_with(b"0123456788"));
+        assert!(footer.invalid());
+        assert!(!footer.valid());
+    }
+
+    #[test]
+    fn test_finish_verify_invalid() {
+        let mut flash = Flash::<24>::new(b"0123456789", 1);
+        let mut slot = Image::from_slice(&mut flash.0);
+
+        let mut crc = Crc::new();
+        crc.update(b"01234");
+        crc.update(b"56788");
+        assert!(!slot.finish_verify(&crc));
+        assert!(slot.footer().invalid());
+        assert!(!slot.needs_checksum());
+
+        let mut footer = Footer(!0);
+        assert!(!image(&mut footer).needs_checksum());
+        assert!(!image(&mut footer).finish_verify(&Crc::new()));
+    }
+
+    #[test]
+    fn test_is_confirmed() {
+        // The markers are set by clearing their bits.
+        let mut confirmed = Footer(footer(1).0 & !(1 << 58));
+        let mut failed = Footer(footer(1).0 & !(1 << 59));
+        let mut pristine = footer(1);
+        assert!(image(&mut confirmed).is_confirmed());
+        assert!(!image(&mut confirmed).is_failed());
+        assert!(!image(&mut failed).is_confirmed());
+        assert!(image(&mut failed).is_failed());
+        assert!(!image(&mut pristine).is_confirmed());
+        assert!(!image(&mut pristine).is_failed());
+
+        let mut image = image(&mut pristine);
+        image.mark_success();
+        assert!(image.is_confirmed());
+        image.mark_failure();
+        assert!(image.is_failed());
+    }
+
+    #[test]
+    fn test_can_retry() {
+        let mut exhausted = Footer(footer(1).0 & !(0xF << 60));
+        assert!(!image(&mut exhausted).can_retry());
+
+        let mut failed = Footer(footer(1).0 & !(1 << 59));
+        assert!(!image(&mut failed).can_retry());
+
+        // The checksum is never consulted, even when it wouldn't match.
+        let mut unverified = Footer(footer(1).0 | 0xAB_CDEF);
+        let bits = unverified.0;
+        assert!(image(&mut unverified).can_retry());
+        assert_eq!(unverified.0, bits);
+    }
+
+    #[test]
+    fn test_boot_counter() {
+        let mut flash = [0xFF; 8];
+        let read = |flash: &[u8]| {
+            let mut word = [0; 4];
+            word.copy_from_slice(&flash[4..]);
+            BootCounter::from_bits(u32::from_ne_bytes(word))
+        };
+        assert_eq!(read(&flash).count(), 0);
+
+        for count in 1..=BootCounter::MAX {
+            let mut counter = read(&flash);
+            let mut writer = BufferWriter {
+                base: 0x0800_0000,
+                buffer: &mut flash,
+            };
+            counter.increment(0x0800_0004, &mut writer);
+            assert_eq!(counter.count(), count);
+            assert_eq!(read(&flash), counter);
+        }
+        assert_eq!(&flash[..4], &[0xFF; 4]);
+
+        // Once saturated, the counter stays put and nothing is written.
+        let mut counter = read(&flash);
+        let mut writer = MockWriter::default();
+        counter.increment(0x0800_0004, &mut writer);
+        assert_eq!(counter.count(), BootCounter::MAX);
+        assert_eq!(writer.writes, 0);
+    }
+
+    #[test]
+    fn test_version_floor() {
+        let mut flash = [0xFF; 4 * VersionFloor::WORDS];
+        let read = |flash: &[u8]| {
+            let mut words = [0; VersionFloor::WORDS];
+            for (word, bytes) in words.iter_mut().zip(flash.chunks(4)) {
+                let mut bytes_a = [0; 4];
+                bytes_a.copy_from_slice(bytes);
+                *word = u32::from_ne_bytes(bytes_a);
+            }
+            VersionFloor::from_words(words)
+        };
+        assert_eq!(read(&flash).version(), 0);
+
+        for &version in &[3, 32, 33, 200, 255] {
+            let mut floor = read(&flash);
+            let mut writer = BufferWriter {
+                base: 0x0800_0000,
+                buffer: &mut flash,
+            };
+            floor.raise(version, 0x0800_0000, &mut writer);
+            assert_eq!(floor.version(), version);
+            assert_eq!(read(&flash), floor);
+        }
+
+        // The floor never falls, and raising it only writes the words which changed.
+        let mut floor = VersionFloor::from_words([!0; VersionFloor::WORDS]);
+        floor.raise(40, 0x0800_0000, &mut MockWriter::default());
+        let mut writer = MockWriter::default();
+        floor.raise(10, 0x0800_0000, &mut writer);
+        assert_eq!((floor.version(), writer.writes), (40, 0));
+        floor.raise(41, 0x0800_0000, &mut writer);
+        assert_eq!(floor.version(), 41);
+        assert_eq!((writer.writes, writer.last_addr), (1, 0x0800_0004));
+    }
+
+    #[test]
+    fn test_select_with_version_floor() {
+        let mut footer_a = footer(2);
+        footer_a.set_valid();
+        let mut footer_b = footer(5);
+        footer_b.set_failure();
+        let mut footer_c = footer(3);
+        footer_c.set_valid();
+        let mut images = [
+            image(&mut footer_a),
+            image(&mut footer_b),
+            image(&mut footer_c),
+        ];
+        assert_eq!(
+            select_with_version_floor(&mut images, 0).map(|image| image.footer().version()),
+            Some(3)
+        );
+
+        // Once version 4 has been confirmed, the remaining images are too old to boot.
+        let mut floor = VersionFloor::from_words([!0; VersionFloor::WORDS]);
+        floor.raise(4, 0, &mut MockWriter::default());
+        assert!(select_with_version_floor(&mut images, floor.version()).is_none());
+        floor.raise(3, 0, &mut MockWriter::default());
+        assert!(select_with_version_floor(&mut images, floor.version()).is_none());
+    }
+
+    #[test]
+    fn test_length_hi() {
+        // A 20 MiB image; its footer alone records only the low 24 bits.
+        let length = 20 << 20;
+        let mut footer = Footer(footer(1).0 & !(0xFF_FFFF << 32) | (length & 0xFF_FFFF) << 32);
+        footer.set_valid();
+        assert_eq!(footer.length(), 4 << 20);
+
+        let addr = &footer as *const Footer as u32;
+        let mut extension = Extension([!0; 2]);
+        assert_eq!(extended(&mut footer, &mut extension).length(), 4 << 20);
+        extension.set_length_hi((length >> 24) as u8);
+        assert_eq!(extension.length_hi(), 1);
+
+        let image = extended(&mut footer, &mut extension);
+        assert_eq!(image.length(), length as u32);
+        assert_eq!(image.start_address(), addr.checked_sub(length as u32));
+        assert_eq!(
+            image.estimated_crc_cycles(),
+            (length as u32 + 3).saturating_mul(crc::CYCLES_PER_BYTE)
+        );
+    }
+
+    #[test]
+    fn test_length_hi_sane() {
+        // A 16 MiB image has all zeros in the footer's length.
+        let mut footer = Footer(footer(1).0 & !(0xFF_FFFF << 32));
+        footer.set_valid();
+        assert!(!footer.sane());
+        let mut extension = Extension([!0; 2]);
+        extension.set_length_hi(1);
+
+        let image = extended(&mut footer, &mut extension);
+        assert_eq!(image.length(), 1 << 24);
+        assert_eq!(image.sane(), image.start_address().is_some());
+    }
+
+    #[test]
+    fn test_select_if() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut footer_c = footer(3);
+        footer_c.set_invalid();
+        let mut images = [
+            image(&mut footer_a),
+            image(&mut footer_b),
+            image(&mut footer_c),
+        ];
+
+        assert!(select_if(&mut images, |_| false).is_none());
+
+        let selected = select_if(&mut images, |_| true);
+        assert_eq!(selected.map(|image| image.footer().version()), Some(2));
+
+        let selected = select_if(&mut images, |image| image.footer().version() != 2);
+        assert_eq!(selected.map(|image| image.footer().version()), Some(1));
+
+        let selected = select_if(&mut images, |image| image.footer().version() == 3);
+        assert!(selected.is_none());
+    }
+
+    #[test]
+    fn test_footer_write_count() {
+        let mut footer = footer(1);
+        let mut extension = Extension([!0; 2]);
+        let mut image = extended(&mut footer, &mut extension);
+        let mut writer = MockWriter::default();
+        assert_eq!(image.footer_write_count(), 0);
+
+        image.footer.set_valid();
+        image.persist(&mut writer);
+        assert_eq!(image.footer_write_count(), 1);
+        assert_eq!(writer.writes, 2);
+        assert_eq!(writer.last_addr, image.footer_address() + 8);
+        assert_eq!(writer.last_len, 16);
+
+        image.prepare_boot();
+        image.persist(&mut writer);
+        image.mark_success();
+        image.persist(&mut writer);
+        assert_eq!(image.footer_write_count(), 3);
+        assert_eq!(writer.writes, 6);
+    }
+
+    #[test]
+    fn test_footer_write_count_saturates() {
+        let mut footer = footer(1);
+        let mut extension = Extension([!0; 2]);
+        extension.set_n_write_count(1);
+        let mut image = extended(&mut footer, &mut extension);
+        let mut writer = MockWriter::default();
+        assert_eq!(image.footer_write_count(), Extension::MAX_WRITE_COUNT - 1);
+
+        image.persist(&mut writer);
+        assert_eq!(image.footer_write_count(), Extension::MAX_WRITE_COUNT);
+        image.persist(&mut writer);
+        assert_eq!(image.footer_write_count(), Extension::MAX_WRITE_COUNT);
+        let extra = image
+            .extension
+            .as_ref()
+            .map(|extension| extension.extra_attempts());
+        assert_eq!(extra, Some(Extension::EXTRA_ATTEMPTS));
+        assert_eq!(writer.writes, 4);
+    }
+
+    #[test]
+    fn test_footer_write_count_without_extension() {
+        let mut footer = footer(1);
+        let mut image = image(&mut footer);
+        let mut writer = MockWriter::default();
+
+        image.persist(&mut writer);
+        assert_eq!(image.footer_write_count(), 0);
+        assert_eq!(writer.writes, 1);
+        assert_eq!(writer.last_addr, image.footer_address());
+        assert_eq!(writer.last_len, 8);
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut footer = Footer(0x3A00_1234_0598_7654);
+        assert_eq!(
+            format!("{:?}", footer),
+            "Footer { checksum: 0x987654, version: 5, length: 4660, valid: true, \
+             invalid: false, success: true, failure: false, attempts: 2 }"
+        );
+
+        let addr = &footer as *const Footer as u32;
+        let mut extension = Extension([!0; 2]);
+        extension.set_booting();
+        let image = extended(&mut footer, &mut extension);
+        let debug = format!("{:?}", image);
+        assert!(debug.starts_with(&format!(
+            "Image {{ index: 0, address: {:#010x}, footer: Footer {{",
+            addr
+        )));
+        assert!(debug.ends_with(
+            "extension: Some(Extension { booting: true, started: false, prefer_other: false, swap_taken: false, writing: false, footer_crc: None, recovery: false, crc_algo: 0, checksum_hi: 255, grace: false, forgiven: false, hw_rev: 255, length_hi: 0, min_bootloader: 0, write_count: 0, extra_attempts: 4 }) }"
+        ));
+    }
+
+    #[test]
+    fn test_request_swap() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut extension_a = Extension([!0; 2]);
+        let mut extension_b = Extension([!0; 2]);
+        let mut image_a = extended(&mut footer_a, &mut extension_a);
+        let mut image_b = extended(&mut footer_b, &mut extension_b);
+
+        assert!(image_b.request_swap());
+        assert_eq!(
+            select(&mut image_a, &mut image_b).map(|image| image.footer().version()),
+            Some(1)
+        );
+        assert!(image_b.extension.as_ref().unwrap().swap_taken());
+        assert_eq!(
+            select(&mut image_a, &mut image_b).map(|image| image.footer().version()),
+            Some(2)
+        );
+
+        // A request can't be made again once it has been honored.
+        assert!(!image_b.request_swap());
+        assert_eq!(
+            select(&mut image_a, &mut image_b).map(|image| image.footer().version()),
+            Some(2)
+        );
+
+        let mut extension_a = Extension([!0; 2]);
+        let mut extension_b = Extension([!0; 2]);
+        let mut image_a = extended(&mut footer_a, &mut extension_a);
+        let mut image_b = extended(&mut footer_b, &mut extension_b);
+        assert!(image_a.request_swap());
+        assert!(image_b.request_swap());
+        assert_eq!(
+            select(&mut image_a, &mut image_b).map(|image| image.footer().version()),
+            Some(2)
+        );
+        assert!(image_a.extension.as_ref().unwrap().swap_taken());
+        assert!(image_b.extension.as_ref().unwrap().swap_taken());
+    }
+
+    #[test]
+    fn test_request_swap_unbootable_sibling() {
+        let mut footer_a = footer(1);
+        footer_a.set_invalid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut extension = Extension([!0; 2]);
+        let mut image_a = image(&mut footer_a);
+        let mut image_b = extended(&mut footer_b, &mut extension);
+
+        assert!(!image_a.request_swap());
+        assert!(image_b.request_swap());
+        assert_eq!(
+            select(&mut image_a, &mut image_b).map(|image| image.footer().version()),
+            Some(2)
+        );
+        assert!(image_b.extension.as_ref().unwrap().swap_taken());
+    }
+
+    #[test]
+    fn test_mark_failure() {
+        let mut footer = footer(1);
+        let mut image = image(&mut footer);
+
+        image.mark_failure();
+        assert!(image.footer.failure());
+        assert!(!image.footer.invalid());
+        assert!(!image.verify_bootable());
+    }
+
+    #[test]
+    fn test_recovery() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut footer_c = footer(3);
+        footer_c.set_valid();
+        let mut extension_a = Extension([!0; 2]);
+        let mut extension_b = Extension([!0; 2]);
+        let mut extension_c = Extension([!0; 2]);
+        extension_c.set_recovery();
+        let mut images = [
+            extended(&mut footer_a, &mut extension_a),
+            extended(&mut footer_b, &mut extension_b),
+            extended(&mut footer_c, &mut extension_c),
+        ];
+        assert!(!images[1].is_recovery());
+        assert!(images[2].is_recovery());
+        assert!(!image(&mut footer(1)).is_recovery());
+
+        // The recovery image is newer and valid, but still ranks below the others.
+        let version = |image: Option<&mut Image>| image.map(|image| image.footer().version());
+        assert_eq!(version(select_by(&mut images, Image::cmp)), Some(2));
+        assert_eq!(version(select_oldest(&mut images)), Some(1));
+
+        images[0].mark_failure();
+        images[1].mark_invalid();
+        let selected = select_by(&mut images, Image::cmp).unwrap();
+        assert_eq!(selected.footer().version(), 3);
+        for _ in 0..2 * Footer::MAX_ATTEMPTS {
+            selected.prepare_boot();
+            selected.prepare_optimistic_boot();
+        }
+        assert_eq!(selected.attempts(), Footer::MAX_ATTEMPTS);
+        assert!(!selected.was_booting());
+        assert!(selected.verify_bootable());
+    }
+
+    #[test]
+    fn test_select_recovery() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut extension_a = Extension([!0; 2]);
+        let mut extension_b = Extension([!0; 2]);
+        extension_b.set_recovery();
+        let mut image_a = extended(&mut footer_a, &mut extension_a);
+        let mut image_b = extended(&mut footer_b, &mut extension_b);
+
+        image_b.request_swap();
+        assert_eq!(
+            select(&mut image_a, &mut image_b)
+                .unwrap()
+                .footer()
+                .version(),
+            1
+        );
+        assert_eq!(
+            select(&mut image_b, &mut image_a)
+                .unwrap()
+                .footer()
+                .version(),
+            1
+        );
+
+        while image_a.attempts() > 0 {
+            image_a.prepare_boot();
+        }
+        let selected = select(&mut image_a, &mut image_b).unwrap();
+        assert_eq!(selected.footer().version(), 2);
+        selected.prepare_boot();
+        assert_eq!(image_b.attempts(), Footer::MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_migrate_from_v1() {
+        // Version 3, 4 KiB long, with all of its status bits erased.
+        let fresh: u64 = 0xFF00_1000_03AB_CDEF;
+        let consumed = fresh & !(1 << 63);
+        let confirmed = fresh & !(1 << 56) & !(1 << 58);
+        let failed = consumed & !(1 << 56) & !(1 << 59);
+        let corrupt = fresh & !(1 << 56) & !(1 << 57);
+        let unsound = consumed & !(1 << 58) & !(1 << 59);
+
+        for &old in &[fresh, consumed, confirmed, failed, corrupt, unsound] {
+            let migrated = Footer::migrate_from_v1(old);
+            assert_eq!(migrated.bits() & !old, 0);
+            assert_eq!(
+                migrated.describe(),
+                FooterInfo {
+                    attempts: (old >> 63) as usize,
+                    ..Footer(old).describe()
+                }
+            );
+        }
+        assert_eq!(Footer::migrate_from_v1(consumed).attempts(), 0);
+        assert_eq!(Footer(consumed).attempts(), 3);
+        assert!(Footer::migrate_from_v1(corrupt).is_corrupt());
+    }
+
+    #[test]
+    fn test_checksum_field_bits() {
+        assert_eq!(Footer::CHECKSUM_FIELD_BITS, crc::CRC_WIDTH_BITS);
+        assert_eq!(
+            Footer(!0).checksum(),
+            (1 << Footer::CHECKSUM_FIELD_BITS) - 1
+        );
+    }
+
+    #[test]
+    fn test_select_with_failure_policy() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        footer_a.set_failure();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        footer_b.set_success();
+
+        for &policy in &[
+            FailurePolicy::StickyFailure,
+            FailurePolicy::FailureClearsOnSiblingSuccess,
+        ] {
+            let mut footer_a = Footer(footer_a.0);
+            let mut footer_b = Footer(footer_b.0);
+            let mut extension_a = Extension([!0; 2]);
+            let mut extension_b = Extension([!0; 2]);
+            let mut image_a = extended(&mut footer_a, &mut extension_a);
+            let mut image_b = extended(&mut footer_b, &mut extension_b);
+            let prior = Footer(image_a.footer().0);
+            let prior_extension = Extension(image_a.extension.as_ref().unwrap().0);
+            let selected = select_with_failure_policy(&mut image_a, &mut image_b, policy);
+            assert_eq!(selected.unwrap().footer().version(), 2);
+
+            let sticky = policy == FailurePolicy::StickyFailure;
+            assert_eq!(image_a.is_failed(), sticky);
+            assert_eq!(image_a.is_bootable(), !sticky);
+            assert!(image_a.footer().failure());
+            assert!(image_a.footer().only_clears(&prior));
+            assert!(image_a
+                .extension
+                .as_ref()
+                .unwrap()
+                .only_clears(&prior_extension));
+
+            // Once the confirmed image is lost, only the forgiven image can take its place.
+            image_b.mark_invalid();
+            let selected = select_with_failure_policy(&mut image_a, &mut image_b, policy);
+            assert_eq!(
+                selected.map(|image| image.footer().version()),
+                if sticky { None } else { Some(1) }
+            );
+
+            // A forgiven image which fails again runs out of attempts instead.
+            image_a.mark_failure();
+            assert!(!image_a.is_bootable());
+            assert_eq!(
+                image_a.attempts(),
+                if sticky { Footer::MAX_ATTEMPTS } else { 0 }
+            );
+        }
+
+        // Neither an image without an extension nor one whose footer is unsound is forgiven.
+        let forgives = |bits: u64, extended: bool| {
+            let mut footer_a = Footer(bits);
+            let mut footer_b = Footer(footer_b.0);
+            let mut extension = Extension([!0; 2]);
+            let mut image_a = Image {
+                extension: if extended { Some(&mut extension) } else { None },
+                ..image(&mut footer_a)
+            };
+            let mut image_b = image(&mut footer_b);
+            let policy = FailurePolicy::FailureClearsOnSiblingSuccess;
+            select_with_failure_policy(&mut image_a, &mut image_b, policy);
+            !image_a.is_failed()
+        };
+        assert!(forgives(footer_a.0, true));
+        assert!(!forgives(footer_a.0, false));
+        assert!(!forgives(footer_a.0 & !(0xFF_FFFF << 32), true));
+
+        // A failure recorded after the sibling was confirmed isn't forgiven.
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        footer_a.set_success();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        footer_b.set_failure();
+        let mut image_a = image(&mut footer_a);
+        let mut image_b = image(&mut footer_b);
+        let selected = select_with_failure_policy(
+            &mut image_a,
+            &mut image_b,
+            FailurePolicy::FailureClearsOnSiblingSuccess,
+        );
+        assert_eq!(selected.unwrap().footer().version(), 1);
+        assert!(image_b.footer().failure());
+    }
+
+    #[test]
+    fn test_total_size() {
+        let mut footer = footer(1);
+        assert_eq!(image(&mut footer).total_size(), 4 + 8);
+
+        let mut flash = Flash::<32>::new(b"sixteen bytes...", 1);
+        let image = Image::from_slice(&mut flash.0);
+        assert_eq!(image.length(), 16);
+        assert_eq!(
+            image.total_size(),
+            image.length() + Footer::FOOTER_SIZE as u32
+        );
+    }
+
+    #[test]
+    fn test_crc_algo() {
+        let contents = b"123456789";
+        let switcher = Flash::<24>::new(contents, 1);
+        let mut ieee = Flash::<24>::new(contents, 2);
+        let checksum = crc::ieee_crc32(contents);
+        let hi = (checksum >> 24) as u8;
+        ieee.0[16..19].copy_from_slice(&checksum.to_le_bytes()[..3]);
+
+        // Verifies a copy of the given flash, whose extension records the given algorithm and
+        // upper checksum byte.
+        let verify = |flash: &Flash<24>, algo: u8, hi: u8| {
+            let mut flash = Flash(flash.0);
+            let mut extension = Extension([!0; 2]);
+            extension.set_crc_algo(algo);
+            extension.set_checksum_hi(hi);
+            let mut image = Image::from_slice(&mut flash.0);
+            image.extension = Some(&mut extension);
+            assert_eq!(image.crc_algo(), algo);
+            let bootable = image.verify_bootable();
+            assert_eq!(image.footer().invalid(), !bootable);
+            bootable
+        };
+
+        assert!(verify(&switcher, CRC_ALGO_SWITCHER, 0xFF));
+        assert!(verify(&ieee, CRC_ALGO_IEEE, hi));
+        // All 32 bits of the CRC-32 are checked, not only those in the footer.
+        assert!(!verify(&ieee, CRC_ALGO_IEEE, hi ^ 1));
+        assert!(!verify(&switcher, CRC_ALGO_IEEE, hi));
+        assert!(!verify(&ieee, CRC_ALGO_SWITCHER, hi));
+        // The upper byte is ignored for the switcher CRC.
+        assert!(verify(&switcher, CRC_ALGO_SWITCHER, 0));
+        assert!(!verify(&switcher, 2, 0xFF));
+        assert!(!verify(&switcher, 3, 0xFF));
+
+        // Images without an extension use the switcher CRC.
+        let mut flash = Flash(switcher.0);
+        let image = Image::from_slice(&mut flash.0);
+        assert_eq!(image.crc_algo(), CRC_ALGO_SWITCHER);
+
+        let mut extension = Extension([!0; 2]);
+        extension.set_crc_algo(CRC_ALGO_IEEE);
+        extension.set_checksum_hi(hi);
+
+        // The CRC fed by the caller only applies to the switcher CRC, so an image using the
+        // CRC-32 can't be verified with it, and is left unmarked.
+        let mut flash = Flash(ieee.0);
+        let mut copy = Extension(extension.0);
+        let mut image = Image::from_slice(&mut flash.0);
+        image.extension = Some(&mut copy);
+        let mut crc = Crc::new();
+        crc.update(contents);
+        assert!(!image.finish_verify(&crc));
+        assert!(!image.footer().valid());
+        assert!(!image.footer().invalid());
+        assert!(image.needs_checksum());
+
+        // A cancellable verification computes the CRC-32 itself.
+        let mut copy = Extension(extension.0);
+        let mut image = Image::from_slice(&mut flash.0);
+        image.extension = Some(&mut copy);
+        assert_eq!(image.verify_bootable_cancellable(4, || true), Some(true));
+        assert!(image.footer().valid());
+
+        // A CRC engine is bypassed for the CRC-32.
+        let mut flash = Flash(ieee.0);
+        let mut copy = Extension(extension.0);
+        let mut image = Image::from_slice(&mut flash.0);
+        image.extension = Some(&mut copy);
+        let mut engine = MockCrcEngine::returning(0);
+        assert!(image.verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 0);
+    }
+
+    #[test]
+    fn test_from_footer() {
+        #[repr(C, align(8))]
+        struct Slot {
+            contents: [u8; 8],
+            footer: Footer,
+        }
+
+        let contents = *b"contents";
+        let checksum = u64::from(crc::calculate(&contents));
+        let mut slot = Slot {
+            contents,
+            footer: Footer(0xFF << 56 | 8 << 32 | 1 << 24 | checksum),
+        };
+        let mut image = unsafe { Image::from_footer(&mut slot.footer) };
+        assert_eq!(image.length(), 8);
+        assert!(image.verify_bootable());
+        assert!(image.footer().valid());
+
+        slot.contents[0] ^= 1;
+        slot.footer = Footer(0xFF << 56 | 8 << 32 | 1 << 24 | checksum);
+        let mut image = unsafe { Image::from_footer(&mut slot.footer) };
+        assert!(!image.verify_bootable());
+        assert!(image.footer().invalid());
+    }
+
+    #[test]
+    fn test_grace() {
+        let grace = |image: &Image| image.extension.as_ref().unwrap().grace();
+
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut extension_a = Extension([!0; 2]);
+        extension_a.set_grace();
+        let mut image_a = extended(&mut footer_a, &mut extension_a);
+        image_a.prepare_boot();
+        assert_eq!(image_a.attempts(), Footer::MAX_ATTEMPTS);
+        assert!(!grace(&image_a));
+        // Using the grace records a marker of its own, leaving the grant in place.
+        assert!(!image_a.extension.as_ref().unwrap().n_grace());
+        image_a.prepare_boot();
+        assert_eq!(image_a.attempts(), Footer::MAX_ATTEMPTS - 1);
+
+        // An optimistic boot only takes the grace once a boot has failed to start.
+        let mut footer_b = footer(1);
+        footer_b.set_valid();
+        let mut extension_b = Extension([!0; 2]);
+        extension_b.set_grace();
+        let mut image_b = extended(&mut footer_b, &mut extension_b);
+        image_b.prepare_optimistic_boot();
+        assert!(grace(&image_b));
+        image_b.prepare_optimistic_boot();
+        assert_eq!(image_b.attempts(), Footer::MAX_ATTEMPTS);
+        assert!(!grace(&image_b));
+        image_b.prepare_optimistic_boot();
+        assert_eq!(image_b.attempts(), Footer::MAX_ATTEMPTS - 1);
+
+        // Without an extension, there is nowhere to record the grace.
+        let mut footer_c = footer(1);
+        image(&mut footer_c).prepare_boot();
+        assert_eq!(footer_c.attempts(), Footer::MAX_ATTEMPTS - 1);
+    }
+
+    #[test]
+    fn test_select_reason() {
+        assert_eq!(
+            select_reason(&mut [] as &mut [Image]),
+            Some(SelectError::NoImages)
+        );
+        let mut erased = Footer(!0);
+        assert_eq!(
+            select_reason(&mut [image(&mut erased)]),
+            Some(SelectError::NoImages)
+        );
+
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut footer_c = Footer(!0);
+        {
+            let mut images = [
+                image(&mut footer_a),
+                image(&mut footer_b),
+                image(&mut footer_c),
+            ];
+            assert_eq!(select_reason(&mut images), None);
+
+            images[0].mark_failure();
+            images[1].mark_failure();
+            assert_eq!(select_reason(&mut images), Some(SelectError::AllFailed));
+        }
+
+        let mut footer_a = verified(1);
+        footer_a.set_n_attempts(0);
+        let mut footer_b = verified(2);
+        footer_b.set_n_attempts(0);
+        let mut images = [image(&mut footer_a), image(&mut footer_b)];
+        assert_eq!(select_reason(&mut images), Some(SelectError::AllExhausted));
+
+        let mut flash_a = Flash::<16>::new(b"ABCD", 1);
+        flash_a.0[4] ^= 0x01;
+        let mut footer_b = footer(2);
+        footer_b.set_invalid();
+        let mut footer_c = Footer(footer(3).0 & !(0xFF_FFFF << 32));
+        let mut images = [
+            Image::from_slice(&mut flash_a.0),
+            image(&mut footer_b),
+            image(&mut footer_c),
+        ];
+        assert_eq!(select_reason(&mut images), Some(SelectError::AllInvalid));
+
+        let mut footer_a = footer(1);
+        footer_a.set_failure();
+        let mut footer_b = verified(2);
+        footer_b.set_n_attempts(0);
+        let mut images = [image(&mut footer_a), image(&mut footer_b)];
+        assert_eq!(select_reason(&mut images), Some(SelectError::Mixed));
+        assert!(select_by(&mut images, Image::cmp).is_none());
+    }
+
+    #[test]
+    fn test_identity() {
+        let mut footer_a = Footer(footer(1).0 | 0x12_3456);
+        let mut footer_b = Footer(footer(1).0 | 0x12_3456);
+        footer_b.set_valid();
+        footer_b.set_success();
+        footer_b.decrement_attempts();
+        let mut footer_c = Footer(footer(1).0 | 0x65_4321);
+        let image_a = image(&mut footer_a);
+        let image_b = image(&mut footer_b);
+        let image_c = image(&mut footer_c);
+
+        assert_eq!(
+            image_a.identity(),
+            ImageIdentity {
+                version: 1,
+                length: 4,
+                checksum: 0x12_3456,
+            }
+        );
+        assert_eq!(image_a.identity(), image_b.identity());
+        assert_ne!(image_a.identity(), image_c.identity());
+        assert!(image_a == image_c);
+    }
+
+    #[test]
+    fn test_rank_bootable() {
+        let mut footers = [footer(2), footer(5), footer(1), footer(5), footer(3)];
+        for footer in footers.iter_mut() {
+            footer.set_valid();
+        }
+        footers[4].set_failure();
+        let [ref mut a, ref mut b, ref mut c, ref mut d, ref mut e] = footers;
+        let mut extension = Extension([!0; 2]);
+        extension.set_recovery();
+        let mut images = [
+            extended(a, &mut extension),
+            image(b),
+            image(c),
+            image(d),
+            image(e),
+        ];
+        let ranked = rank_bootable(&mut images);
+        assert_eq!(ranked, [Some(3), Some(1), Some(2), Some(0), None]);
+
+        let best = select_by(&mut images, Image::cmp).map(|image| image as *const Image);
+        assert_eq!(best, Some(&images[3] as *const Image));
+        assert_eq!(rank_bootable(&mut [] as &mut [Image; 0]), []);
+    }
+
+    #[test]
+    fn test_consume_attempt() {
+        for start in 0..=Footer::MAX_ATTEMPTS {
+            let mut footer = footer(1);
+            while footer.attempts() > start {
+                footer.decrement_attempts();
+            }
+            let mut image = image(&mut footer);
+            assert_eq!(image.consume_attempt(), start.saturating_sub(1));
+            assert_eq!(image.attempts(), start.saturating_sub(1));
+        }
+
+        let mut footer = footer(1);
+        let mut image = Image::<2> {
+            footer: &mut footer,
+            extension: None,
+            build_info: None,
+            cached_crc: None,
+            index: 0,
+        };
+        assert_eq!(image.consume_attempt(), 1);
+        assert_eq!(image.consume_attempt(), 0);
+        assert_eq!(image.consume_attempt(), 0);
+    }
+
+    #[test]
+    fn test_boot_state() {
+        let mut state = BootState::from_bits(!0);
+        assert_eq!(state.active_slot(), None);
+        assert_eq!(state.rollback_counter(), 0);
+        assert_eq!(state.boot_loop_count(), 0);
+
+        state.set_active_slot(1);
+        assert_eq!(state.active_slot(), Some(1));
+        state.raise_rollback_counter(3);
+        state.raise_rollback_counter(2);
+        assert_eq!(state.rollback_counter(), 3);
+        for _ in 0..300 {
+            state.increment_boot_loop_count();
+        }
+        assert_eq!(state.boot_loop_count(), 255);
+        state.reset_boot_loop_count();
+        assert_eq!(state.boot_loop_count(), 0);
+        assert_eq!(BootState::from_bits(state.bits()).active_slot(), Some(1));
+
+        assert_eq!(state.stuck_slot(), None);
+        state.set_stuck_slot(0, 7);
+        assert_eq!((state.stuck_slot(), state.stuck_version()), (Some(0), 7));
+        state.clear_stuck_slot();
+        assert_eq!(state.stuck_slot(), None);
+    }
+
+    #[test]
+    fn test_select_with_state() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        footer_a.set_success();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut state = BootState::from_bits(!0);
+        {
+            let mut images = [image(&mut footer_a), image(&mut footer_b)];
+
+            // The trial image is active, and its boots are counted.
+            let selected = select_with_state(&mut images, &mut state).unwrap();
+            assert_eq!(selected.footer().version(), 2);
+            assert_eq!(state.active_slot(), Some(1));
+            assert_eq!(state.boot_loop_count(), 1);
+            assert_eq!(state.rollback_counter(), 0);
+
+            // The trial fails, so the confirmed image is fallen back upon.
+            images[1].mark_failure();
+            let selected = select_with_state(&mut images, &mut state).unwrap();
+            assert_eq!(selected.footer().version(), 1);
+            assert_eq!(state.active_slot(), Some(0));
+            assert_eq!(state.boot_loop_count(), 0);
+            assert_eq!(state.rollback_counter(), 1);
+        }
+
+        // A newer confirmed image raises the floor above the older one.
+        let mut footer_c = footer(3);
+        footer_c.set_valid();
+        footer_c.set_success();
+        let mut images = [image(&mut footer_a), image(&mut footer_c)];
+        select_with_state(&mut images, &mut state).unwrap();
+        assert_eq!(state.rollback_counter(), 3);
+        images[1].mark_invalid();
+        assert!(select_with_state(&mut images, &mut state).is_none());
+        assert!(images[0].verify_bootable());
+        assert_eq!(state.active_slot(), Some(1));
+    }
+
+    #[test]
+    fn test_verify_self() {
+        let mut sealed = footer(1);
+        let mut extension = Extension([!0; 2]);
+        let mut sealed_image = extended(&mut sealed, &mut extension);
+        assert!(sealed_image.verify_self());
+        assert!(sealed_image.seal_footer());
+        assert!(sealed_image.verify_self());
+
+        sealed_image.footer.0 ^= 1 << 33;
+        assert!(!sealed_image.verify_self());
+        assert!(!sealed_image.verify_bootable());
+        assert!(sealed_image.footer().invalid());
+
+        let mut unsound = Footer(footer(1).0 & !(0xFF_FFFF << 32));
+        assert!(!image(&mut unsound).verify_self());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut footer = footer(1);
+        let blank = footer.0;
+        let mut image = image(&mut footer);
+
+        let mut engine = MockCrcEngine::returning(0);
+        assert!(image.verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 1);
+
+        // The contents are rewritten, and the footer reset, behind the image's back.
+        let mut engine = MockCrcEngine::returning(0x12_3456);
+        image.footer.0 = blank;
+        assert!(image.verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 0);
+
+        image.footer.0 = blank;
+        image.refresh();
+        assert_eq!(image.cached_result(), None);
+        assert!(!image.verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 1);
+        assert_eq!(image.cached_result(), Some(false));
+    }
+
+    #[test]
+    fn test_select_with_loop_detection() {
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut footer_b = footer(2);
+        footer_b.set_valid();
+        let mut footer_c = footer(3);
+        footer_c.set_valid();
+        let mut extension = Extension([!0; 2]);
+        extension.set_recovery();
+        let mut state = BootState::from_bits(!0);
+        {
+            let mut images = [
+                extended(&mut footer_a, &mut extension),
+                image(&mut footer_b),
+                image(&mut footer_c),
+            ];
+
+            // The newest image keeps resetting before it is confirmed.
+            for count in 1..=3 {
+                let selected = select_with_loop_detection(&mut images, &mut state, 3).unwrap();
+                assert_eq!(selected.footer().version(), 3);
+                selected.prepare_boot();
+                assert_eq!(state.boot_loop_count(), count);
+            }
+            assert!(images[2].verify_bootable());
+
+            let selected = select_with_loop_detection(&mut images, &mut state, 3).unwrap();
+            assert_eq!(selected.footer().version(), 2);
+            assert_eq!(state.active_slot(), Some(1));
+            assert_eq!(state.boot_loop_count(), 1);
+            assert_eq!(state.stuck_slot(), Some(2));
+
+            // The fallback sticks, however many times it is booted.
+            for count in 2..=5 {
+                let selected = select_with_loop_detection(&mut images, &mut state, 3).unwrap();
+                assert_eq!(selected.footer().version(), 2);
+                assert_eq!(state.boot_loop_count(), count);
+            }
+
+            // Once the stuck image has been replaced, it is tried again.
+            images[2].footer.0 = footer(4).0;
+            images[2].footer.set_valid();
+            let selected = select_with_loop_detection(&mut images, &mut state, 3).unwrap();
+            assert_eq!(selected.footer().version(), 4);
+            assert_eq!(state.stuck_slot(), None);
+            assert_eq!(state.boot_loop_count(), 1);
+        }
+
+        // With only a recovery image to fall back on, it is selected.
+        let mut footer_c = footer(3);
+        footer_c.set_valid();
+        let mut state = BootState::from_bits(!0);
+        let mut images = [
+            extended(&mut footer_a, &mut extension),
+            image(&mut footer_c),
+        ];
+        for _ in 0..2 {
+            select_with_loop_detection(&mut images, &mut state, 2).unwrap();
+        }
+        let selected = select_with_loop_detection(&mut images, &mut state, 2).unwrap();
+        assert!(selected.is_recovery());
+
+        // Without a limit, the looping image keeps being selected.
+        let mut state = BootState::from_bits(!0);
+        for _ in 0..10 {
+            select_with_state(&mut images, &mut state).unwrap();
+        }
+        assert_eq!(state.active_slot(), Some(1));
+        assert_eq!(state.boot_loop_count(), 10);
+    }
+
+    #[test]
+    fn test_only_clears() {
+        let transitions: [fn(&mut Footer); 5] = [
+            Footer::set_valid,
+            Footer::set_invalid,
+            Footer::set_success,
+            Footer::set_failure,
+            Footer::decrement_attempts,
+        ];
+        let mut prior = footer(1);
+        prior.set_valid();
+        prior.decrement_attempts();
+        for &bits in &[footer(1).0, prior.0, 0] {
+            let start = Footer(bits);
+            for transition in &transitions {
+                let mut changed = Footer(start.0);
+                transition(&mut changed);
+                assert!(
+                    changed.only_clears(&start),
+                    "{:?} from {:?}",
+                    changed,
+                    start
+                );
+            }
+        }
+        let mut changed = Footer(prior.0);
+        changed.reset_attempts();
+        assert!(!changed.only_clears(&prior));
+
+        let transitions: [fn(&mut Extension); 9] = [
+            Extension::set_booting,
+            Extension::set_started,
+            Extension::set_prefer_other,
+            Extension::set_swap_taken,
+            Extension::set_writing,
+            Extension::set_recovery,
+            Extension::set_grace,
+            Extension::use_grace,
+            Extension::set_forgiven,
+        ];
+        for &bits in &[[!0; 2], [0xFF00_FF00_FF00_FF00; 2]] {
+            let start = Extension(bits);
+            for transition in &transitions {
+                let mut changed = Extension(start.0);
+                transition(&mut changed);
+                assert!(
+                    changed.only_clears(&start),
+                    "{:?} from {:?}",
+                    changed,
+                    start
+                );
+            }
+        }
+        let erased = Extension([!0; 2]);
+        let mut changed = Extension([!0; 2]);
+        changed.set_footer_crc(0x1234);
+        changed.set_crc_algo(CRC_ALGO_IEEE);
+        changed.set_length_hi(0x12);
+        changed.set_min_bootloader(3);
+        assert!(changed.only_clears(&erased));
+        // The second write sets the count's lowest bit again.
+        changed.increment_write_count();
+        let mut counted = Extension(changed.0);
+        counted.increment_write_count();
+        assert!(!counted.only_clears(&changed));
+
+        // Every transition made while booting an image only clears bits, whichever path it
+        // takes through them.
+        fn clears_only(image: &mut Image, step: &fn(&mut Image)) {
+            let prior = Footer(image.footer().0);
+            let prior_extension = Extension(image.extension.as_ref().unwrap().0);
+            step(image);
+            assert!(image.footer().only_clears(&prior), "{:?}", image);
+            assert!(
+                image
+                    .extension
+                    .as_ref()
+                    .unwrap()
+                    .only_clears(&prior_extension),
+                "{:?}",
+                image
+            );
+        }
+        let confirmed: [fn(&mut Image); 13] = [
+            |image| assert!(image.seal_footer()),
+            |image| assert!(image.request_swap()),
+            |image| assert!(image.verify_bootable_using(&mut MockCrcEngine::returning(0))),
+            |image| assert!(image.take_swap_request()),
+            |image| assert!(image.mark_booting()),
+            |image| {
+                image.prepare_optimistic_boot();
+            },
+            |image| {
+                image.prepare_optimistic_boot();
+            },
+            |image| image.mark_started(),
+            |image| {
+                image.prepare_optimistic_boot();
+            },
+            |image| {
+                image.consume_attempt();
+            },
+            |image| {
+                image.take_grace();
+            },
+            |image| image.mark_success(),
+            |image| {
+                image.prepare_boot();
+            },
+        ];
+        let failed: [fn(&mut Image); 7] = [
+            |image| assert!(image.verify_bootable_using(&mut MockCrcEngine::returning(0))),
+            |image| {
+                image.prepare_boot();
+            },
+            |image| image.mark_failure(),
+            |image| {
+                let mut sibling = verified(2);
+                sibling.set_success();
+                forgive_failure(image, &self::image(&mut sibling));
+                assert!(!image.is_failed());
+            },
+            |image| image.mark_failure(),
+            |image| image.mark_invalid(),
+            |image| image.mark_failure(),
+        ];
+        for steps in &[&confirmed[..], &failed[..]] {
+            let mut footer_a = footer(1);
+            let mut extension = Extension([!0; 2]);
+            extension.set_grace();
+            let mut image_a = extended(&mut footer_a, &mut extension);
+            for step in steps.iter() {
+                clears_only(&mut image_a, step);
+            }
+        }
+
+        // An image confirmed with one attempt left reports a full budget without setting bits.
+        let mut footer_b = footer(2);
+        let mut image_b = image(&mut footer_b);
+        for _ in 1..Footer::MAX_ATTEMPTS {
+            image_b.prepare_boot();
+        }
+        assert_eq!(image_b.attempts(), 1);
+        let prior = Footer(image_b.footer().0);
+        image_b.mark_success();
+        assert!(image_b.footer().only_clears(&prior));
+        assert_eq!(image_b.attempts(), Footer::MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_write_changes() {
+        let mut buffer = [0xFF; 8];
+        {
+            let mut writer = BufferWriter {
+                base: 0x100,
+                buffer: &mut buffer,
+            };
+            let new = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF];
+            assert!(flash::write_changes(&mut writer, 0x100, &[0xFF; 8], &new));
+            assert!(!flash::write_changes(&mut writer, 0x100, &new, &[0xFF; 8]));
+        }
+        assert_eq!(buffer, [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF]);
+
+        let mut writer = MockWriter::default();
+        assert!(flash::write_changes(
+            &mut writer,
+            0x100,
+            &[0xAA; 8],
+            &[0xAA; 8]
+        ));
+        assert_eq!(writer.writes, 0);
+        assert!(flash::write_changes(
+            &mut writer,
+            0x100,
+            &[0xFF; 8],
+            &[0; 8]
+        ));
+        assert_eq!(
+            (writer.writes, writer.last_addr, writer.last_len),
+            (2, 0x104, 4)
+        );
+    }
+
+    #[test]
+    fn test_persist_changes() {
+        let mut footer_a = footer(1);
+        let mut extension = Extension([!0; 2]);
+        let mut image_a = extended(&mut footer_a, &mut extension);
+        let addr = image_a.footer_address();
+
+        let prior = Footer(image_a.footer().0);
+        let prior_extension = Extension([!0; 2]);
+        image_a.mark_success();
+        let mut writer = MockWriter::default();
+        assert!(image_a.persist_changes(&mut writer, &prior, Some(&prior_extension)));
+        // Only the footer's status word changed.
+        assert_eq!(
+            (writer.writes, writer.last_addr, writer.last_len),
+            (1, addr + 4, 4)
+        );
+        assert_eq!(image_a.footer_write_count(), 0);
+
+        let prior = Footer(image_a.footer().0);
+        image_a.footer.reset_status();
+        let mut writer = MockWriter::default();
+        assert!(!image_a.persist_changes(&mut writer, &prior, Some(&prior_extension)));
+        assert!(!image_a.persist_changes(&mut writer, image_a.footer(), None));
+        assert_eq!(writer.writes, 0);
+    }
+
+    #[test]
+    fn test_verify_detail() {
+        let mut flash = Flash::<32>::new(b"0123456789", 1);
+        let checksum = crc::calculate(b"0123456789");
+        {
+            let image_a = Image::from_slice(&mut flash.0);
+            let detail = image_a.verify_detail();
+            assert_eq!(
+                detail,
+                VerifyDetail {
+                    stored: checksum,
+                    computed: Some(checksum),
+                    length: 10,
+                    start: Some(image_a.footer_address() - 10),
+                }
+            );
+            assert!(detail.matches());
+            assert!(!image_a.footer().valid());
+        }
+
+        flash.0[20] ^= 0x01;
+        let corrupt = crc::calculate(&flash.0[14..24]);
+        let image_a = Image::from_slice(&mut flash.0);
+        let detail = image_a.verify_detail();
+        assert_eq!(detail.stored, checksum);
+        assert_eq!(detail.computed, Some(corrupt));
+        assert_eq!(detail.length, 10);
+        assert_eq!(detail.start, Some(image_a.footer_address() - 10));
+        assert!(!detail.matches());
+        assert!(!image_a.footer().valid() && !image_a.footer().invalid());
+    }
+
+    /// Returns the contents "abc" followed by their SHA-256 digest.
+    fn digested() -> [u8; 35] {
+        let mut contents = [0; 35];
+        contents[..3].copy_from_slice(b"abc");
+        contents[3..].copy_from_slice(&[
+            0xBA, 0x78, 0x16, 0xBF, 0x8F, 0x01, 0xCF, 0xEA, 0x41, 0x41, 0x40, 0xDE, 0x5D, 0xAE,
+            0x22, 0x23, 0xB0, 0x03, 0x61, 0xA3, 0x96, 0x17, 0x7A, 0x9C, 0xB4, 0x10, 0xFF, 0x61,
+            0xF2, 0x00, 0x15, 0xAD,
+        ]);
+        contents
+    }
+
+    #[test]
+    fn test_digest_fast_path() {
+        let info = BuildInfo(!0 << 33);
+        assert!(info.has_digest());
+        assert!(!BuildInfo(!0).has_digest());
+
+        // The checksum covers the digest, which plays no part in verifying the image.
+        let mut flash = Flash::<48>::new(&digested(), 1);
+        let mut image_a = Image::from_slice(&mut flash.0);
+        image_a.build_info = Some(&info);
+        assert!(image_a.has_digest());
+        assert!(image_a.verify_bootable());
+
+        let mut flash = Flash::<48>::new(&digested(), 1);
+        flash.0[40 - 1] ^= 0x01;
+        let mut image_b = Image::from_slice(&mut flash.0);
+        image_b.build_info = Some(&info);
+        assert!(!image_b.verify_bootable());
+
+        let mut flash = Flash::<48>::new(&digested(), 1);
+        assert!(!Image::from_slice(&mut flash.0).has_digest());
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn test_deep_verify() {
+        let info = BuildInfo(!0 << 33);
+        let mut flash = Flash::<48>::new(&digested(), 1);
+        {
+            let mut image_a = Image::from_slice(&mut flash.0);
+            assert!(!image_a.deep_verify());
+            image_a.build_info = Some(&info);
+            assert!(image_a.deep_verify());
+            assert!(!image_a.footer().valid());
+        }
+
+        // Corrupting either the contents or the digest is caught.
+        for &offset in &[5, 39] {
+            let mut corrupt = Flash::<48>::new(&digested(), 1);
+            corrupt.0[offset] ^= 0x01;
+            let mut image_b = Image::from_slice(&mut corrupt.0);
+            image_b.build_info = Some(&info);
+            assert!(!image_b.deep_verify());
+        }
+
+        let mut flash = Flash::<48>::new(&digested()[..DIGEST_BYTES - 1], 1);
+        let mut image_c = Image::from_slice(&mut flash.0);
+        image_c.build_info = Some(&info);
+        assert!(!image_c.deep_verify());
+    }
+
+    #[test]
+    fn test_select_or_recover() {
+        let policies = [
+            RecoveryAction::EnterDfu(0x1FFF_0000),
+            RecoveryAction::Halt,
+            RecoveryAction::Reset,
+        ];
+        for &policy in &policies {
+            let mut footer_a = footer(1);
+            footer_a.set_invalid();
+            let mut footer_b = footer(2);
+            footer_b.set_invalid();
+            let mut images = [image(&mut footer_a), image(&mut footer_b)];
+            match select_or_recover(&mut images, policy) {
+                SelectOutcome::Recover(action) => assert_eq!(action, policy),
+                outcome => panic!("unexpected outcome: {:?}", outcome),
+            }
+            match select_or_recover(&mut [] as &mut [Image], policy) {
+                SelectOutcome::Recover(action) => assert_eq!(action, policy),
+                outcome => panic!("unexpected outcome: {:?}", outcome),
+            }
+        }
+
+        // A bootable image, even a recovery image, is returned ahead of the action.
+        let mut footer_a = footer(1);
+        footer_a.set_valid();
+        let mut extension = Extension([!0; 2]);
+        extension.set_recovery();
+        let mut footer_b = footer(2);
+        footer_b.set_invalid();
+        let mut images = [
+            extended(&mut footer_a, &mut extension),
+            image(&mut footer_b),
+        ];
+        match select_or_recover(&mut images, RecoveryAction::Halt) {
+            SelectOutcome::Boot(selected) => assert!(selected.is_recovery()),
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
     }
 }
@@ -0,0 +1,206 @@
+// Copyright 2015 Alex Crawford
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Host simulation of the boot handoff.
+//!
+//! With the `sim` feature enabled, [`Image::boot()`][boot] records the [`Handoff`][handoff] it
+//! would have jumped to and then panics instead of executing the jump. This allows the full boot
+//! path to be exercised by host tests (using `std::panic::catch_unwind()` to recover).
+//!
+//! The processor's interrupt mask is also simulated. Just as on the target, interrupts must be
+//! disabled before the jump; a handoff made with interrupts enabled is not recorded.
+//!
+//! Likewise, [`select_and_boot()`][select_and_boot] records that it halted, rather than waiting
+//! for interrupts forever, and then panics.
+//!
+//! [select_and_boot]: ../fn.select_and_boot.html
+//! [boot]: ../struct.Image.html#method.boot
+//! [handoff]: ../struct.Handoff.html
+
+use std::cell::Cell;
+
+use Handoff;
+
+thread_local! {
+    static HANDOFF: Cell<Option<Handoff>> = const { Cell::new(None) };
+    static INTERRUPTS_ENABLED: Cell<bool> = const { Cell::new(true) };
+    static HALTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Disables interrupts for the current thread.
+pub(crate) fn disable_interrupts() {
+    INTERRUPTS_ENABLED.with(|i| i.set(false))
+}
+
+/// Records the given handoff for the current thread.
+///
+/// The handoff is only recorded if interrupts have been disabled.
+pub(crate) fn record_handoff(handoff: Handoff) {
+    if !interrupts_enabled() {
+        HANDOFF.with(|h| h.set(Some(handoff)))
+    }
+}
+
+/// Records that the current thread's processor has halted.
+pub(crate) fn record_halt() {
+    HALTED.with(|h| h.set(true))
+}
+
+/// Returns true if interrupts are enabled on the current thread.
+pub fn interrupts_enabled() -> bool {
+    INTERRUPTS_ENABLED.with(|i| i.get())
+}
+
+/// Simulates a reset of the current thread's processor.
+///
+/// Interrupts are re-enabled and any recorded handoff or halt is cleared.
+pub fn reset() {
+    INTERRUPTS_ENABLED.with(|i| i.set(true));
+    HANDOFF.with(|h| h.set(None));
+    HALTED.with(|h| h.set(false))
+}
+
+/// Returns the handoff recorded by the most recent simulated boot on the current thread, if any.
+///
+/// The recorded handoff is cleared.
+pub fn take_handoff() -> Option<Handoff> {
+    HANDOFF.with(|h| h.take())
+}
+
+/// Returns true if the current thread's processor has halted, waiting for an image to boot.
+pub fn halted() -> bool {
+    HALTED.with(|h| h.get())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use tests::image;
+    use {select, select_and_boot, Footer, Image};
+
+    /// Boots the given image, returning the recorded handoff.
+    fn boot(image: &mut Image) -> Option<Handoff> {
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| image.boot())).is_err());
+        take_handoff()
+    }
+
+    #[repr(C, align(8))]
+    struct Flash {
+        image_a: [u8; 8],
+        footer_a: Footer,
+        image_b: [u8; 8],
+        footer_b: Footer,
+    }
+
+    #[test]
+    fn test_boot() {
+        let mut flash = Flash {
+            image_a: [0; 8],
+            footer_a: Footer(0xFF << 56 | 8 << 32 | 1 << 24),
+            image_b: [0; 8],
+            footer_b: Footer(0xFF << 56 | 8 << 32 | 2 << 24),
+        };
+        flash.footer_a.set_valid();
+        flash.footer_b.set_valid();
+        let start_b = flash.footer_b.start_address();
+
+        let mut image_a = image(&mut flash.footer_a);
+        let mut image_b = image(&mut flash.footer_b);
+
+        let handoff = boot(select(&mut image_a, &mut image_b).unwrap());
+        assert_eq!(handoff, start_b.map(|pc| Handoff { sp: 0, pc }));
+        assert_eq!(image_a.footer().attempts(), Footer::MAX_ATTEMPTS);
+        assert_eq!(image_b.footer().attempts(), Footer::MAX_ATTEMPTS - 1);
+        assert_eq!(take_handoff(), None);
+
+        for _ in 1..Footer::MAX_ATTEMPTS {
+            boot(select(&mut image_a, &mut image_b).unwrap());
+        }
+        assert_eq!(image_b.footer().attempts(), 0);
+
+        let handoff = boot(select(&mut image_a, &mut image_b).unwrap());
+        assert_eq!(handoff.map(|h| h.pc), image_a.footer().start_address());
+        assert_eq!(image_a.footer().attempts(), Footer::MAX_ATTEMPTS - 1);
+    }
+
+    #[test]
+    fn test_select_and_boot() {
+        let mut flash = Flash {
+            image_a: [0; 8],
+            footer_a: Footer(0xFF << 56 | 8 << 32 | 1 << 24),
+            image_b: [0; 8],
+            footer_b: Footer(0xFF << 56 | 8 << 32 | 2 << 24),
+        };
+        flash.footer_a.set_valid();
+        flash.footer_b.set_valid();
+        let start_a = flash.footer_a.start_address();
+
+        {
+            let mut images = [image(&mut flash.footer_a), image(&mut flash.footer_b)];
+            images[1].mark_failure();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| select_and_boot(&mut images)));
+            assert!(result.is_err());
+            assert_eq!(take_handoff(), start_a.map(|pc| Handoff { sp: 0, pc }));
+            assert!(!halted());
+
+            images[0].mark_invalid();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| select_and_boot(&mut images)));
+            assert!(result.is_err());
+            assert_eq!(take_handoff(), None);
+            assert!(halted());
+        }
+
+        reset();
+        assert!(!halted());
+        assert_eq!(flash.footer_a.attempts(), Footer::MAX_ATTEMPTS - 1);
+    }
+
+    #[test]
+    fn test_boot_disables_interrupts() {
+        let mut footer = Footer(0xFF << 56 | 8 << 32 | 1 << 24);
+        let mut image = image(&mut footer);
+        assert!(interrupts_enabled());
+
+        assert!(boot(&mut image).is_some());
+        assert!(!interrupts_enabled());
+
+        reset();
+        assert!(interrupts_enabled());
+        assert_eq!(take_handoff(), None);
+    }
+
+    #[test]
+    fn test_record_handoff_interrupts_enabled() {
+        let handoff = Handoff { sp: 0, pc: 0x1000 };
+
+        record_handoff(handoff);
+        assert_eq!(take_handoff(), None);
+
+        disable_interrupts();
+        record_handoff(handoff);
+        assert_eq!(take_handoff(), Some(handoff));
+    }
+
+    #[test]
+    fn test_boot_success() {
+        let mut footer = Footer(0xFF << 56 | 8 << 32 | 1 << 24);
+        let mut image = image(&mut footer);
+
+        image.mark_success();
+        assert!(boot(&mut image).is_some());
+        assert!(boot(&mut image).is_some());
+        assert_eq!(image.footer().attempts(), Footer::MAX_ATTEMPTS);
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright 2015 Alex Crawford
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for host tests.
+//!
+//! This module is available with the `testing` feature enabled. It is intended for tests of
+//! integrations, which need footers in particular states without spelling out their bits, or
+//! checksums which match (or don't) without computing them.
+
+use crc::CrcEngine;
+use Footer;
+
+/// A builder of footers in arbitrary states.
+///
+/// The builder starts from a pristine footer (see [`Footer::new()`][new]) of version zero, with
+/// no length or checksum and the full [`Footer::MAX_ATTEMPTS`][max] attempts. Markers are set in
+/// the footer regardless of whether the combination could arise in practice, so that unsound and
+/// corrupt footers can be built as well.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::testing::FooterBuilder;
+/// let footer = FooterBuilder::new()
+///     .version(3)
+///     .length(0x1000)
+///     .attempts(1)
+///     .valid(true)
+///     .build();
+/// assert_eq!(footer.version(), 3);
+/// assert_eq!(footer.attempts(), 1);
+/// ```
+///
+/// [new]: ../struct.Footer.html#method.new
+/// [max]: ../struct.Footer.html#associatedconstant.MAX_ATTEMPTS
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FooterBuilder {
+    version: u8,
+    length: u32,
+    checksum: u32,
+    attempts: usize,
+    valid: bool,
+    invalid: bool,
+    success: bool,
+    failure: bool,
+}
+
+impl FooterBuilder {
+    /// Creates a builder of a pristine footer.
+    pub fn new() -> FooterBuilder {
+        FooterBuilder {
+            version: 0,
+            length: 0,
+            checksum: 0,
+            attempts: Footer::MAX_ATTEMPTS,
+            valid: false,
+            invalid: false,
+            success: false,
+            failure: false,
+        }
+    }
+    /// Sets the version of the image.
+    pub fn version(mut self, version: u8) -> FooterBuilder {
+        self.version = version;
+        self
+    }
+    /// Sets the length of the image, of which only the low 24 bits are recorded.
+    pub fn length(mut self, length: u32) -> FooterBuilder {
+        self.length = length;
+        self
+    }
+    /// Sets the checksum of the image.
+    pub fn checksum(mut self, checksum: u32) -> FooterBuilder {
+        self.checksum = checksum;
+        self
+    }
+    /// Sets the number of remaining boot attempts, at most `Footer::MAX_ATTEMPTS`.
+    pub fn attempts(mut self, attempts: usize) -> FooterBuilder {
+        self.attempts = attempts;
+        self
+    }
+    /// Sets whether the image has been marked valid.
+    pub fn valid(mut self, valid: bool) -> FooterBuilder {
+        self.valid = valid;
+        self
+    }
+    /// Sets whether the image has been marked invalid.
+    pub fn invalid(mut self, invalid: bool) -> FooterBuilder {
+        self.invalid = invalid;
+        self
+    }
+    /// Sets whether the image has been marked as having successfully booted.
+    pub fn success(mut self, success: bool) -> FooterBuilder {
+        self.success = success;
+        self
+    }
+    /// Sets whether the image has been marked as having failed to boot.
+    pub fn failure(mut self, failure: bool) -> FooterBuilder {
+        self.failure = failure;
+        self
+    }
+    /// Builds the footer.
+    pub fn build(&self) -> Footer {
+        let mut footer = Footer::new(self.version, self.length, self.checksum);
+        if self.valid {
+            footer.set_valid();
+        }
+        if self.invalid {
+            footer.set_invalid();
+        }
+        if self.success {
+            footer.set_success();
+        }
+        if self.failure {
+            footer.set_failure();
+        }
+        while footer.attempts() > self.attempts {
+            footer.decrement_attempts();
+        }
+        footer
+    }
+}
+
+impl Default for FooterBuilder {
+    fn default() -> FooterBuilder {
+        FooterBuilder::new()
+    }
+}
+
+/// A CRC engine which returns a fixed sum, whatever the data.
+///
+/// Passing this to `Image::verify_bootable_using()` forces the outcome of the checksum
+/// comparison, so that selection logic can be tested without building images with real
+/// checksums.
+///
+/// # Examples
+///
+/// ```
+/// # use switcher::Image;
+/// # use switcher::testing::{FooterBuilder, MockCrcEngine};
+/// # #[repr(align(8))]
+/// # struct Flash([u8; 16]);
+/// let footer = FooterBuilder::new().length(4).checksum(0x12_3456).build();
+/// let mut flash = Flash([0; 16]);
+/// flash.0[8..].copy_from_slice(&footer.bits().to_ne_bytes());
+///
+/// let mut engine = MockCrcEngine::matching(&footer);
+/// assert!(Image::from_slice(&mut flash.0).verify_bootable_using(&mut engine));
+/// assert_eq!(engine.runs(), 1);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MockCrcEngine {
+    checksum: u32,
+    runs: usize,
+}
+
+impl MockCrcEngine {
+    /// Creates an engine which computes the given sum.
+    pub fn returning(checksum: u32) -> MockCrcEngine {
+        MockCrcEngine { checksum, runs: 0 }
+    }
+    /// Creates an engine which computes the checksum recorded in the given footer.
+    pub fn matching(footer: &Footer) -> MockCrcEngine {
+        MockCrcEngine::returning(footer.checksum())
+    }
+    /// Creates an engine which computes a sum other than the checksum recorded in the given
+    /// footer.
+    pub fn mismatching(footer: &Footer) -> MockCrcEngine {
+        MockCrcEngine::returning(footer.checksum() ^ 1)
+    }
+    /// Returns the number of sums the engine has computed.
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+}
+
+impl CrcEngine for MockCrcEngine {
+    fn compute(&mut self, _data: &[u8]) -> u32 {
+        self.runs += 1;
+        self.checksum
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use FooterInfo;
+    use Image;
+
+    #[test]
+    fn test_pristine() {
+        let footer = FooterBuilder::new().build();
+        assert_eq!(footer.bits(), Footer::new(0, 0, 0).bits());
+        assert_eq!(footer.attempts(), Footer::MAX_ATTEMPTS);
+        assert!(!footer.is_erased());
+    }
+
+    #[test]
+    fn test_states() {
+        let trial = FooterBuilder::new()
+            .version(7)
+            .length(0x12_3456)
+            .checksum(0xA7_629E)
+            .attempts(2)
+            .valid(true);
+        assert_eq!(
+            trial.build().describe(),
+            FooterInfo {
+                checksum: 0xA7_629E,
+                version: 7,
+                length: 0x12_3456,
+                valid: true,
+                invalid: false,
+                success: false,
+                failure: false,
+                attempts: 2,
+            }
+        );
+
+        let confirmed = trial.success(true).build();
+        assert!(confirmed.success() && confirmed.valid());
+        assert_eq!(confirmed.describe().attempts, 2);
+
+        let exhausted = trial.attempts(0).build();
+        assert_eq!(exhausted.attempts(), 0);
+
+        let failed = trial.failure(true).build();
+        assert!(failed.failure() && !failed.success());
+
+        let corrupt = trial.invalid(true).build();
+        assert!(corrupt.is_corrupt());
+
+        let unsound = trial.success(true).failure(true).build();
+        assert!(!unsound.sane());
+
+        // Only the low 24 bits of the length are recorded.
+        assert_eq!(trial.length(0x0100_0004).build().length(), 4);
+    }
+
+    /// A buffer, aligned like flash, for an image of four bytes and its footer.
+    #[repr(C, align(8))]
+    struct Flash([u8; 16]);
+
+    impl Flash {
+        fn new(footer: &Footer) -> Flash {
+            let mut flash = Flash([0; 16]);
+            flash.0[8..].copy_from_slice(&footer.bits().to_ne_bytes());
+            flash
+        }
+    }
+
+    #[test]
+    fn test_mock_crc_engine() {
+        let footer = FooterBuilder::new().length(4).checksum(0x12_3456).build();
+
+        let mut flash = Flash::new(&footer);
+        let mut engine = MockCrcEngine::matching(&footer);
+        {
+            let mut image = Image::from_slice(&mut flash.0);
+            assert!(image.verify_bootable_using(&mut engine));
+            assert!(image.footer().valid());
+        }
+        // Once recorded, the validity is taken from the footer.
+        assert!(Image::from_slice(&mut flash.0).verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 1);
+
+        let mut flash = Flash::new(&footer);
+        let mut engine = MockCrcEngine::mismatching(&footer);
+        let mut image = Image::from_slice(&mut flash.0);
+        assert!(!image.verify_bootable_using(&mut engine));
+        assert!(image.footer().invalid());
+        assert_eq!(engine.runs(), 1);
+
+        let mut flash = Flash::new(&FooterBuilder::new().length(4).failure(true).build());
+        let mut engine = MockCrcEngine::returning(0);
+        assert!(!Image::from_slice(&mut flash.0).verify_bootable_using(&mut engine));
+        assert_eq!(engine.runs(), 0);
+    }
+}